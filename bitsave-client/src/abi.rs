@@ -0,0 +1,196 @@
+//! Builds the JSON ABI from what the contract exports: the Solidity interface
+//! printed by `cargo stylus export-abi`, plus the `sol!` event and error
+//! declarations, which the Stylus export leaves out.
+
+use std::collections::BTreeMap;
+
+use ethers::abi::{parse_abi, Abi, EventParam, Param, ParamType};
+
+/// Declarations in `source` starting with `keyword`, without the trailing `;`
+fn declarations<'a>(source: &'a str, keyword: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(move |line| line.starts_with(keyword))
+        .map(|line| line.trim_end_matches(';'))
+}
+
+/// Parses the exported interface and the `sol!` blocks (events.rs, errors.rs)
+pub fn from_sources(interface: &str, sol_sources: &[&str]) -> Result<Abi, String> {
+    let mut lines: Vec<&str> = declarations(interface, "function ").collect();
+    for source in sol_sources {
+        lines.extend(declarations(source, "event "));
+        lines.extend(declarations(source, "error "));
+    }
+
+    parse_abi(&lines).map_err(|err| err.to_string())
+}
+
+/// Serialises an ABI the way archive/abi.json is committed
+pub fn to_json(abi: &Abi) -> String {
+    let mut json = serde_json::to_string_pretty(abi).expect("ABI serialises");
+    json.push('\n');
+    json
+}
+
+pub fn from_json(json: &str) -> Result<Abi, String> {
+    serde_json::from_str(json).map_err(|err| err.to_string())
+}
+
+fn params(params: &[Param]) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|p| format!("{} {}", p.kind, p.name).trim_end().to_string())
+        .collect();
+    params.join(", ")
+}
+
+fn event_params(params: &[EventParam]) -> String {
+    let params: Vec<String> = params
+        .iter()
+        .map(|p| match p.indexed {
+            true => format!("{} indexed {}", p.kind, p.name),
+            false => format!("{} {}", p.kind, p.name),
+        })
+        .collect();
+    params.join(", ")
+}
+
+fn types<'a>(kinds: impl Iterator<Item = &'a ParamType>) -> String {
+    kinds.map(ToString::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// Signature -> full declaration of every function, event and error
+fn entries(abi: &Abi) -> BTreeMap<String, String> {
+    let mut entries = BTreeMap::new();
+    for function in abi.functions() {
+        let signature = format!(
+            "function {}({})",
+            function.name,
+            types(function.inputs.iter().map(|p| &p.kind))
+        );
+        let declaration = format!(
+            "function {}({}) {} returns ({})",
+            function.name,
+            params(&function.inputs),
+            format!("{:?}", function.state_mutability).to_lowercase(),
+            params(&function.outputs),
+        );
+        entries.insert(signature, declaration);
+    }
+
+    for event in abi.events() {
+        let signature = format!(
+            "event {}({})",
+            event.name,
+            types(event.inputs.iter().map(|p| &p.kind))
+        );
+        let declaration = format!("event {}({})", event.name, event_params(&event.inputs));
+        entries.insert(signature, declaration);
+    }
+
+    for error in abi.errors() {
+        let signature = format!(
+            "error {}({})",
+            error.name,
+            types(error.inputs.iter().map(|p| &p.kind))
+        );
+        let declaration = format!("error {}({})", error.name, params(&error.inputs));
+        entries.insert(signature, declaration);
+    }
+
+    entries
+}
+
+/// Readable differences between the committed ABI and the current one, one
+/// line per added (`+`), removed (`-`) or changed (`~`) entry
+pub fn diff(committed: &Abi, current: &Abi) -> Vec<String> {
+    let committed = entries(committed);
+    let current = entries(current);
+
+    let mut changes = Vec::new();
+    for (signature, declaration) in &committed {
+        match current.get(signature) {
+            None => changes.push(format!("- {}", declaration)),
+            Some(now) if now != declaration => {
+                changes.push(format!("~ {}\n    was {}", now, declaration))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for (signature, declaration) in &current {
+        if !committed.contains_key(signature) {
+            changes.push(format!("+ {}", declaration));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INTERFACE: &str = "
+        interface IBitsave {
+            function fund() external payable returns (uint256);
+            function getNonce(address user) external view returns (uint256);
+        }";
+    const EVENTS: &str = "
+        sol! {
+            event UserJoined(address indexed user, uint256 user_id);
+            event Initialized(uint64 version);
+        }";
+
+    fn abi(interface: &str, events: &str) -> Abi {
+        from_sources(interface, &[events]).unwrap()
+    }
+
+    #[test]
+    fn same_abi_has_no_changes() {
+        let committed = abi(INTERFACE, EVENTS);
+        let current = from_json(&to_json(&committed)).unwrap();
+        assert!(diff(&committed, &current).is_empty());
+    }
+
+    #[test]
+    fn reports_added_removed_and_changed_entries() {
+        let committed = abi(INTERFACE, EVENTS);
+        let current = abi(
+            "
+            function fund() external returns (uint256);
+            function getNonce(address user) external view returns (uint256);
+            function getVaultState() external view returns (uint256);",
+            "event UserJoined(address indexed user, uint256 user_id);",
+        );
+
+        assert_eq!(
+            diff(&committed, &current),
+            [
+                "- event Initialized(uint64 version)",
+                "~ function fund() nonpayable returns (uint256)\n    was function fund() payable returns (uint256)",
+                "+ function getVaultState() view returns (uint256)",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_a_changed_signature_as_a_removal_and_an_addition() {
+        let committed = abi(INTERFACE, EVENTS);
+        let current = abi(
+            "
+            function fund() external payable returns (uint256);
+            function getNonce(address user, uint256 index) external view returns (uint256);",
+            EVENTS,
+        );
+
+        assert_eq!(
+            diff(&committed, &current),
+            [
+                "- function getNonce(address user) view returns (uint256)",
+                "+ function getNonce(address user, uint256 index) view returns (uint256)",
+            ]
+        );
+    }
+}
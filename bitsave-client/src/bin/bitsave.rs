@@ -0,0 +1,970 @@
+//! `bitsave`: command-line wallet for the Bitsave program.
+//!
+//! Reads `RPC_URL`, `PRIV_KEY_PATH` and `STYLUS_PROGRAM_ADDRESS` from the
+//! environment or a `.env` file, like the examples. Transactions run through
+//! `eth_call` with `--dry-run`; `--json` switches the output to JSON.
+//!
+//! ```text
+//! cargo run -p bitsave-client --bin bitsave -- help
+//! ```
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bitsave_client::{
+    payable::{self, CreateSaving, Join},
+    revert::CallError,
+    statement::{parse_date, Statement},
+    Bitsave,
+};
+use dotenv::dotenv;
+use ethers::{
+    abi::Detokenize,
+    contract::{abigen, ContractCall, ContractError},
+    middleware::SignerMiddleware,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    types::{Address, H256, U256},
+    utils::{format_ether, format_units, parse_ether},
+};
+use eyre::eyre;
+use serde_json::{json, Value};
+
+/// Your private key file path.
+const PRIV_KEY_PATH: &str = "PRIV_KEY_PATH";
+
+/// Stylus RPC endpoint url.
+const RPC_URL: &str = "RPC_URL";
+
+/// Deployed program address.
+const STYLUS_PROGRAM_ADDRESS: &str = "STYLUS_PROGRAM_ADDRESS";
+
+const USAGE: &str = "\
+usage: bitsave [--dry-run] [--json] <command> [args]
+
+commands:
+  join <user-name> [--fee AMOUNT]
+  create-saving <name> --amount AMOUNT (--days N | --maturity TIMESTAMP)
+                [--penalty PERC] [--safe-mode]
+  increment <name> --amount AMOUNT
+  withdraw <name>
+  list-savings [--user ADDRESS]
+  show-saving <name> [--user ADDRESS]
+  balances
+  statement --from DATE --to DATE [--user ADDRESS] [--from-block N] [--out FILE]
+  admin config
+  admin queue <operation> [--salt HEX]
+  admin execute <operation> [--salt HEX]
+  admin cancel <operation-id>
+  admin eta <operation-id>
+  admin close-migration
+  admin strategy <token>
+  admin rebalance <token>
+  admin harvest <token>
+
+operations:
+  set-config <min-join-fee|year-in-seconds|interest-divisor|max-supply|total-supply> <value>
+  transfer-admin <address>
+  pause | unpause
+  upgrade <implementation>
+  open-migration
+  set-strategy <vault> <share-bps>
+  spend-pool <token> <amount>
+  set-vault-state <value>
+
+AMOUNT is in ether (`0.05`) unless it ends in `wei` (`50000wei`).
+DATE is `YYYY-MM-DD` (UTC, `--to` inclusive) or a unix timestamp; statements
+print CSV, or JSON with `--json`.";
+
+/// Governance operation kinds, see `constants::governance` in the contract
+const SET_CONFIG: u8 = 0;
+const TRANSFER_ADMIN: u8 = 1;
+const SET_PAUSED: u8 = 2;
+const UPGRADE: u8 = 3;
+const OPEN_MIGRATION: u8 = 4;
+const SET_STRATEGY: u8 = 5;
+const SPEND_POOL: u8 = 6;
+const SET_VAULT_STATE: u8 = 7;
+
+/// Config parameters by name, see `constants::config` in the contract
+const CONFIG_PARAMS: [&str; 5] = [
+    "min-join-fee",
+    "year-in-seconds",
+    "interest-divisor",
+    "max-supply",
+    "total-supply",
+];
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+type Client = SignerMiddleware<Provider<Http>, LocalWallet>;
+
+abigen!(
+    Erc20,
+    r#"[function decimals() external view returns (uint8)]"#
+);
+
+/// Positional arguments and `--option value` pairs of one command
+struct Args {
+    positional: Vec<String>,
+    options: BTreeMap<String, String>,
+    switches: Vec<String>,
+}
+
+impl Args {
+    /// Options that take no value
+    const SWITCHES: [&'static str; 3] = ["--dry-run", "--json", "--safe-mode"];
+
+    fn parse(mut raw: impl Iterator<Item = String>) -> eyre::Result<Self> {
+        let mut args = Args {
+            positional: Vec::new(),
+            options: BTreeMap::new(),
+            switches: Vec::new(),
+        };
+
+        while let Some(arg) = raw.next() {
+            if Self::SWITCHES.contains(&arg.as_str()) {
+                args.switches.push(arg);
+            } else if arg.starts_with("--") {
+                let value = raw.next().ok_or_else(|| eyre!("{} needs a value", arg))?;
+                args.options.insert(arg, value);
+            } else {
+                args.positional.push(arg);
+            }
+        }
+        Ok(args)
+    }
+
+    fn switch(&self, name: &str) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn required(&self, name: &str) -> eyre::Result<&str> {
+        self.option(name)
+            .ok_or_else(|| eyre!("{} is required", name))
+    }
+
+    fn arg(&self, index: usize, what: &str) -> eyre::Result<&str> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| eyre!("missing <{}>", what))
+    }
+}
+
+/// What a command prints, as text or as JSON
+struct Output {
+    text: String,
+    json: Value,
+}
+
+impl Output {
+    fn print(&self, as_json: bool) {
+        if as_json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&self.json).unwrap_or_default()
+            );
+        } else {
+            println!("{}", self.text);
+        }
+    }
+}
+
+/// One saving as returned by `get_saving` and `get_saving_plan`
+struct SavingView {
+    name: String,
+    token: Address,
+    amount: U256,
+    interest: U256,
+    start_time: U256,
+    maturity_time: U256,
+    penalty_perc: u8,
+    safe_mode: bool,
+    /// Decimals of an ERC-20 token, when it reports them
+    decimals: Option<u32>,
+    /// (amount, interval, next_due, missed_count) of an active plan
+    plan: Option<(U256, U256, U256, U256)>,
+}
+
+impl SavingView {
+    fn to_text(&self) -> String {
+        let mut text = format!(
+            "{}\n  amount:     {}\n  interest:   {}\n  started:    {}\n  maturity:   {}\n  penalty:    {}%\n  safe mode:  {}",
+            self.name,
+            display_amount(self.amount, self.token, self.decimals),
+            display_amount(self.interest, self.token, self.decimals),
+            display_time(self.start_time),
+            display_time(self.maturity_time),
+            self.penalty_perc,
+            self.safe_mode,
+        );
+        if let Some((amount, interval, next_due, missed)) = self.plan {
+            text.push_str(&format!(
+                "\n  plan:       {} every {}s, next due {}, {} missed",
+                display_amount(amount, self.token, self.decimals),
+                interval,
+                display_time(next_due),
+                missed,
+            ));
+        }
+        text
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "name": self.name,
+            "token": format!("{:?}", self.token),
+            "decimals": self.decimals,
+            "amount": self.amount.to_string(),
+            "interest_accumulated": self.interest.to_string(),
+            "start_time": json_number(self.start_time),
+            "maturity_time": json_number(self.maturity_time),
+            "matured": self.maturity_time <= U256::from(now()),
+            "penalty_perc": self.penalty_perc,
+            "safe_mode": self.safe_mode,
+            "plan": self.plan.map(|(amount, interval, next_due, missed)| json!({
+                "amount": amount.to_string(),
+                "interval": json_number(interval),
+                "next_due": json_number(next_due),
+                "missed": json_number(missed),
+            })),
+        })
+    }
+}
+
+/// A connected wallet and the program it talks to
+struct Wallet {
+    bitsave: Bitsave<Client>,
+    client: Arc<Client>,
+    dry_run: bool,
+}
+
+impl Wallet {
+    async fn connect(dry_run: bool) -> eyre::Result<Self> {
+        let rpc_url = std::env::var(RPC_URL).map_err(|_| eyre!("No {} env var set", RPC_URL))?;
+        let program_address: Address = std::env::var(STYLUS_PROGRAM_ADDRESS)
+            .map_err(|_| eyre!("No {} env var set", STYLUS_PROGRAM_ADDRESS))?
+            .parse()?;
+        let priv_key_path =
+            std::env::var(PRIV_KEY_PATH).map_err(|_| eyre!("No {} env var set", PRIV_KEY_PATH))?;
+
+        let provider = Provider::<Http>::try_from(rpc_url)?;
+        let privkey = read_secret_from_file(&priv_key_path)?;
+        let wallet = LocalWallet::from_str(&privkey)?;
+        let chain_id = provider.get_chainid().await?.as_u64();
+        let client = Arc::new(SignerMiddleware::new(
+            provider,
+            wallet.with_chain_id(chain_id),
+        ));
+
+        Ok(Wallet {
+            bitsave: Bitsave::new(program_address, client.clone()),
+            client,
+            dry_run,
+        })
+    }
+
+    fn address(&self) -> Address {
+        self.client.address()
+    }
+
+    /// Simulates the call with `--dry-run`, otherwise sends it and waits for the receipt
+    async fn transact<D>(&self, action: &str, call: ContractCall<Client, D>) -> eyre::Result<Output>
+    where
+        D: Detokenize + std::fmt::Debug,
+    {
+        if self.dry_run {
+            let result = call.call().await.map_err(describe)?;
+            return Ok(Output {
+                text: format!("{}: would succeed, returns {:?}", action, result),
+                json: json!({
+                    "action": action,
+                    "dry_run": true,
+                    "result": format!("{:?}", result),
+                }),
+            });
+        }
+
+        let pending = call.send().await.map_err(describe)?;
+        let tx_hash = pending.tx_hash();
+        let receipt = pending
+            .await?
+            .ok_or_else(|| eyre!("{:?} was dropped from the mempool", tx_hash))?;
+        let success = receipt.status.map(|s| s.as_u64() == 1).unwrap_or(false);
+
+        Ok(Output {
+            text: format!(
+                "{}: {} in block {} ({:?}, gas used {})",
+                action,
+                if success { "confirmed" } else { "failed" },
+                receipt.block_number.unwrap_or_default(),
+                tx_hash,
+                receipt.gas_used.unwrap_or_default(),
+            ),
+            json: json!({
+                "action": action,
+                "dry_run": false,
+                "tx_hash": format!("{:?}", tx_hash),
+                "block": receipt.block_number.map(|b| b.as_u64()),
+                "status": success,
+                "gas_used": receipt.gas_used.map(|g| g.to_string()),
+            }),
+        })
+    }
+
+    async fn join(&self, args: &Args) -> eyre::Result<Output> {
+        let mut join = Join::new(&self.bitsave, args.arg(0, "user-name")?);
+        if let Some(fee) = args.option("--fee") {
+            join = join.fee(parse_amount(fee)?);
+        }
+        let call = join.into_call().await.map_err(describe)?;
+        self.transact("join", call).await
+    }
+
+    async fn create_saving(&self, args: &Args) -> eyre::Result<Output> {
+        let mut saving = CreateSaving::new(&self.bitsave, args.arg(0, "name")?)
+            .amount(parse_amount(args.required("--amount")?)?)
+            .safe_mode(args.switch("--safe-mode"));
+
+        saving = match (args.option("--days"), args.option("--maturity")) {
+            (Some(days), None) => saving.maturity_in(days.parse::<u64>()? * SECONDS_PER_DAY),
+            (None, Some(timestamp)) => saving.maturity(timestamp.parse()?),
+            _ => return Err(eyre!("pass exactly one of --days or --maturity")),
+        };
+        if let Some(penalty) = args.option("--penalty") {
+            saving = saving.penalty(penalty.parse()?);
+        }
+
+        self.transact("create-saving", saving.into_call()).await
+    }
+
+    async fn increment(&self, args: &Args) -> eyre::Result<Output> {
+        let amount = parse_amount(args.required("--amount")?)?;
+        let call = payable::increment_saving(&self.bitsave, args.arg(0, "name")?, amount);
+        self.transact("increment", call).await
+    }
+
+    async fn withdraw(&self, args: &Args) -> eyre::Result<Output> {
+        let call = self
+            .bitsave
+            .withdraw_savings(args.arg(0, "name")?.to_string());
+        self.transact("withdraw", call).await
+    }
+
+    /// Decimals of an ERC-20 token; ETH is formatted as ether instead
+    async fn decimals(&self, token: Address) -> Option<u32> {
+        if token.is_zero() {
+            return None;
+        }
+        let erc20 = Erc20::new(token, self.client.clone());
+        erc20.decimals().call().await.ok().map(u32::from)
+    }
+
+    fn user(&self, args: &Args) -> eyre::Result<Address> {
+        match args.option("--user") {
+            Some(user) => Ok(user.parse()?),
+            None => Ok(self.address()),
+        }
+    }
+
+    async fn saving(&self, user: Address, name: &str) -> eyre::Result<SavingView> {
+        let (is_valid, amount, maturity_time, start_time, token, safe_mode, interest, penalty_perc) =
+            self.bitsave
+                .get_saving(user, name.to_string())
+                .call()
+                .await
+                .map_err(describe)?;
+        if !is_valid {
+            return Err(eyre!("{:?} has no saving named `{}`", user, name));
+        }
+
+        let (plan_active, _, plan_amount, interval, next_due, missed, _) = self
+            .bitsave
+            .get_saving_plan(user, name.to_string())
+            .call()
+            .await
+            .map_err(describe)?;
+
+        Ok(SavingView {
+            name: name.to_string(),
+            token,
+            amount,
+            interest,
+            start_time,
+            maturity_time,
+            penalty_perc,
+            safe_mode,
+            decimals: self.decimals(token).await,
+            plan: plan_active.then_some((plan_amount, interval, next_due, missed)),
+        })
+    }
+
+    async fn list_savings(&self, args: &Args) -> eyre::Result<Output> {
+        let user = self.user(args)?;
+        let names = self
+            .bitsave
+            .get_savings_names(user)
+            .call()
+            .await
+            .map_err(describe)?;
+
+        let mut savings = Vec::new();
+        let mut lines = vec![format!("{} savings of {:?}", names.len(), user)];
+        for name in names {
+            let saving = self.saving(user, &name).await?;
+            lines.push(format!(
+                "  {:<20} {:>24}  matures {}",
+                saving.name,
+                display_amount(saving.amount, saving.token, saving.decimals),
+                display_time(saving.maturity_time),
+            ));
+            savings.push(saving.to_json());
+        }
+
+        Ok(Output {
+            text: lines.join("\n"),
+            json: json!({ "user": format!("{:?}", user), "savings": savings }),
+        })
+    }
+
+    async fn show_saving(&self, args: &Args) -> eyre::Result<Output> {
+        let saving = self.saving(self.user(args)?, args.arg(0, "name")?).await?;
+        Ok(Output {
+            text: saving.to_text(),
+            json: saving.to_json(),
+        })
+    }
+
+    async fn statement(&self, args: &Args) -> eyre::Result<Output> {
+        let from = parse_time(args.required("--from")?, 0)?;
+        let to = parse_time(args.required("--to")?, SECONDS_PER_DAY - 1)?;
+        let from_block = match args.option("--from-block") {
+            Some(block) => block.parse()?,
+            None => 0,
+        };
+
+        let statement = Statement::fetch(&self.bitsave, self.user(args)?, from, to, from_block)
+            .await
+            .map_err(describe)?;
+        let output = Output {
+            text: statement.to_csv(),
+            json: statement.to_json(),
+        };
+
+        match args.option("--out") {
+            Some(path) => {
+                let contents = if args.switch("--json") {
+                    serde_json::to_string_pretty(&output.json)?
+                } else {
+                    output.text
+                };
+                std::fs::write(path, contents)?;
+                Ok(Output {
+                    text: format!("{} entries written to {}", statement.entries.len(), path),
+                    json: json!({ "entries": statement.entries.len(), "out": path }),
+                })
+            }
+            None => Ok(output),
+        }
+    }
+
+    async fn balances(&self) -> eyre::Result<Output> {
+        let address = self.address();
+        let wallet = self.client.get_balance(address, None).await?;
+        let pending = self
+            .bitsave
+            .get_pending_withdrawal()
+            .call()
+            .await
+            .map_err(describe)?;
+        let general_fund = self
+            .bitsave
+            .get_bitsave_balance()
+            .call()
+            .await
+            .map_err(describe)?;
+        let accumulated_pool = self
+            .bitsave
+            .get_accumulated_pool()
+            .call()
+            .await
+            .map_err(describe)?;
+        let token_pool = self
+            .bitsave
+            .get_tokens_balance()
+            .call()
+            .await
+            .map_err(describe)?;
+
+        Ok(Output {
+            text: format!(
+                "wallet {:?}\n  balance:             {} ETH\n  pending withdrawal:  {} ETH\nprotocol\n  general fund:        {} ETH\n  accumulated pool:    {}\n  token pool:          {}",
+                address,
+                format_ether(wallet),
+                format_ether(pending),
+                format_ether(general_fund),
+                accumulated_pool,
+                token_pool,
+            ),
+            json: json!({
+                "address": format!("{:?}", address),
+                "balance": wallet.to_string(),
+                "pending_withdrawal": pending.to_string(),
+                "general_fund": general_fund.to_string(),
+                "accumulated_pool": accumulated_pool.to_string(),
+                "token_pool": token_pool.to_string(),
+            }),
+        })
+    }
+
+    async fn admin(&self, args: &Args) -> eyre::Result<Output> {
+        match args.arg(0, "admin command")? {
+            "config" => self.config().await,
+            "queue" => {
+                let (kind, param, value, account) = parse_operation(&args.positional[1..])?;
+                let salt = parse_salt(args.option("--salt"))?;
+                let id = self
+                    .bitsave
+                    .queue_operation(kind, param, value, account, salt)
+                    .call()
+                    .await
+                    .map_err(describe)?;
+                let call = self
+                    .bitsave
+                    .queue_operation(kind, param, value, account, salt);
+                let mut output = self.transact("queue", call).await?;
+                output
+                    .text
+                    .push_str(&format!("\noperation id {:?}", H256(id)));
+                output.json["operation_id"] = json!(format!("{:?}", H256(id)));
+                Ok(output)
+            }
+            "execute" => {
+                let (kind, param, value, account) = parse_operation(&args.positional[1..])?;
+                let salt = parse_salt(args.option("--salt"))?;
+                let call = self
+                    .bitsave
+                    .execute_operation(kind, param, value, account, salt);
+                self.transact("execute", call).await
+            }
+            "cancel" => {
+                let id = parse_operation_id(args.arg(1, "operation-id")?)?;
+                self.transact("cancel", self.bitsave.cancel_operation(id))
+                    .await
+            }
+            "eta" => {
+                let id = parse_operation_id(args.arg(1, "operation-id")?)?;
+                let eta = self
+                    .bitsave
+                    .get_operation_eta(id)
+                    .call()
+                    .await
+                    .map_err(describe)?;
+                let text = if eta.is_zero() {
+                    "not queued".to_string()
+                } else {
+                    format!("executable from {}", display_time(eta))
+                };
+                Ok(Output {
+                    text,
+                    json: json!({ "operation_id": format!("{:?}", H256(id)), "eta": json_number(eta) }),
+                })
+            }
+            "close-migration" => {
+                self.transact("close-migration", self.bitsave.close_migration())
+                    .await
+            }
+            "strategy" => {
+                let token: Address = args.arg(1, "token")?.parse()?;
+                let (vault, share_bps, deployed, position) = self
+                    .bitsave
+                    .get_strategy(token)
+                    .call()
+                    .await
+                    .map_err(describe)?;
+                let pool = self
+                    .bitsave
+                    .get_token_accumulated_pool(token)
+                    .call()
+                    .await
+                    .map_err(describe)?;
+                Ok(Output {
+                    text: format!(
+                        "vault:     {:?}\nshare:     {} bps\ndeployed:  {}\nposition:  {}\npool:      {}",
+                        vault, share_bps, deployed, position, pool
+                    ),
+                    json: json!({
+                        "token": format!("{:?}", token),
+                        "vault": format!("{:?}", vault),
+                        "share_bps": share_bps.to_string(),
+                        "deployed": deployed.to_string(),
+                        "position": position.to_string(),
+                        "accumulated_pool": pool.to_string(),
+                    }),
+                })
+            }
+            "rebalance" => {
+                let token = args.arg(1, "token")?.parse()?;
+                self.transact("rebalance", self.bitsave.rebalance_strategy(token))
+                    .await
+            }
+            "harvest" => {
+                let token = args.arg(1, "token")?.parse()?;
+                self.transact("harvest", self.bitsave.harvest_strategy(token))
+                    .await
+            }
+            other => Err(eyre!("unknown admin command `{}`\n\n{}", other, USAGE)),
+        }
+    }
+
+    async fn config(&self) -> eyre::Result<Output> {
+        let (admin, paused, min_join_fee, year_in_seconds, divisor, max_supply, total_supply) =
+            self.bitsave.get_config().call().await.map_err(describe)?;
+        let vault_state = self
+            .bitsave
+            .get_vault_state()
+            .call()
+            .await
+            .map_err(describe)?;
+
+        Ok(Output {
+            text: format!(
+                "admin:             {:?}\npaused:            {}\nmin-join-fee:      {} ETH\nyear-in-seconds:   {}\ninterest-divisor:  {}\nmax-supply:        {}\ntotal-supply:      {}\nvault-state:       {}",
+                admin,
+                paused,
+                format_ether(min_join_fee),
+                year_in_seconds,
+                divisor,
+                max_supply,
+                total_supply,
+                vault_state,
+            ),
+            json: json!({
+                "admin": format!("{:?}", admin),
+                "paused": paused,
+                "min_join_fee": min_join_fee.to_string(),
+                "year_in_seconds": year_in_seconds.to_string(),
+                "interest_divisor": divisor.to_string(),
+                "max_supply": max_supply.to_string(),
+                "total_supply": total_supply.to_string(),
+                "vault_state": vault_state.to_string(),
+            }),
+        })
+    }
+}
+
+/// `0.05` is ether, `50000wei` is wei
+fn parse_amount(amount: &str) -> eyre::Result<U256> {
+    match amount.strip_suffix("wei") {
+        Some(wei) => Ok(U256::from_dec_str(wei.trim())?),
+        None => Ok(parse_ether(amount.trim())?),
+    }
+}
+
+/// Maps `<operation> [args]` to the `(kind, param, value, account)` the timelock hashes
+fn parse_operation(words: &[String]) -> eyre::Result<(u8, u8, U256, Address)> {
+    let word = |index: usize, what: &str| {
+        words
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| eyre!("missing <{}>", what))
+    };
+
+    let operation = match word(0, "operation")? {
+        "set-config" => {
+            let name = word(1, "param")?;
+            let param = CONFIG_PARAMS
+                .iter()
+                .position(|p| *p == name)
+                .ok_or_else(|| eyre!("unknown config param `{}`", name))?;
+            let raw = word(2, "value")?;
+            let value = if name == "min-join-fee" {
+                parse_amount(raw)?
+            } else {
+                U256::from_dec_str(raw)?
+            };
+            (SET_CONFIG, param as u8, value, Address::zero())
+        }
+        "transfer-admin" => (
+            TRANSFER_ADMIN,
+            0,
+            U256::zero(),
+            word(1, "address")?.parse()?,
+        ),
+        "pause" => (SET_PAUSED, 0, U256::one(), Address::zero()),
+        "unpause" => (SET_PAUSED, 0, U256::zero(), Address::zero()),
+        "upgrade" => (
+            UPGRADE,
+            0,
+            U256::zero(),
+            word(1, "implementation")?.parse()?,
+        ),
+        "open-migration" => (OPEN_MIGRATION, 0, U256::zero(), Address::zero()),
+        "set-strategy" => (
+            SET_STRATEGY,
+            0,
+            U256::from_dec_str(word(2, "share-bps")?)?,
+            word(1, "vault")?.parse()?,
+        ),
+        "spend-pool" => {
+            let token: Address = word(1, "token")?.parse()?;
+            let raw = word(2, "amount")?;
+            let amount = if token.is_zero() {
+                parse_amount(raw)?
+            } else {
+                U256::from_dec_str(raw)?
+            };
+            (SPEND_POOL, 0, amount, token)
+        }
+        "set-vault-state" => (
+            SET_VAULT_STATE,
+            0,
+            U256::from_dec_str(word(1, "value")?)?,
+            Address::zero(),
+        ),
+        other => return Err(eyre!("unknown operation `{}`", other)),
+    };
+    Ok(operation)
+}
+
+/// A `YYYY-MM-DD` date plus `day_offset` seconds, or a unix timestamp
+fn parse_time(time: &str, day_offset: u64) -> eyre::Result<u64> {
+    if let Some(midnight) = parse_date(time) {
+        return Ok(midnight + day_offset);
+    }
+    time.parse()
+        .map_err(|_| eyre!("expected YYYY-MM-DD or a unix timestamp, got `{}`", time))
+}
+
+fn parse_salt(salt: Option<&str>) -> eyre::Result<[u8; 32]> {
+    match salt {
+        Some(salt) => Ok(H256::from_str(salt)?.0),
+        None => Ok([0u8; 32]),
+    }
+}
+
+fn parse_operation_id(id: &str) -> eyre::Result<[u8; 32]> {
+    Ok(H256::from_str(id)?.0)
+}
+
+/// Ether amounts in ether, token amounts in the token's decimals (raw units
+/// if it has none) with the token address
+fn display_amount(amount: U256, token: Address, decimals: Option<u32>) -> String {
+    if token.is_zero() {
+        return format!("{} ETH", format_ether(amount));
+    }
+    match decimals.and_then(|decimals| format_units(amount, decimals).ok()) {
+        Some(units) => format!("{} of {:?}", units, token),
+        None => format!("{} of {:?}", amount, token),
+    }
+}
+
+/// A JSON number, or the decimal string for values past `u64`
+fn json_number(value: U256) -> Value {
+    if value > U256::from(u64::MAX) {
+        json!(value.to_string())
+    } else {
+        json!(value.low_u64())
+    }
+}
+
+fn display_time(timestamp: U256) -> String {
+    if timestamp > U256::from(u64::MAX) {
+        return timestamp.to_string();
+    }
+    let timestamp = timestamp.low_u64();
+    let now = now();
+    if timestamp > now {
+        format!(
+            "{} (in {:.1} days)",
+            timestamp,
+            (timestamp - now) as f64 / SECONDS_PER_DAY as f64
+        )
+    } else {
+        format!(
+            "{} ({:.1} days ago)",
+            timestamp,
+            (now - timestamp) as f64 / SECONDS_PER_DAY as f64
+        )
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
+
+/// Turns a failed call into a readable error, decoding contract reverts
+fn describe(err: ContractError<Client>) -> eyre::Report {
+    eyre!(CallError::from(err))
+}
+
+async fn run() -> eyre::Result<()> {
+    dotenv().ok();
+    let mut args = Args::parse(std::env::args().skip(1))?;
+    let as_json = args.switch("--json");
+
+    if args.positional.first().is_none_or(|c| c == "help") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let command = args.positional.remove(0);
+
+    let wallet = Wallet::connect(args.switch("--dry-run")).await?;
+    let output = match command.as_str() {
+        "join" => wallet.join(&args).await?,
+        "create-saving" => wallet.create_saving(&args).await?,
+        "increment" => wallet.increment(&args).await?,
+        "withdraw" => wallet.withdraw(&args).await?,
+        "list-savings" => wallet.list_savings(&args).await?,
+        "show-saving" => wallet.show_saving(&args).await?,
+        "balances" => wallet.balances().await?,
+        "statement" => wallet.statement(&args).await?,
+        "admin" => wallet.admin(&args).await?,
+        other => return Err(eyre!("unknown command `{}`\n\n{}", other, USAGE)),
+    };
+    output.print(as_json);
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() {
+    if let Err(err) = run().await {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn read_secret_from_file(fpath: &str) -> eyre::Result<String> {
+    let f = std::fs::File::open(fpath)?;
+    let mut buf_reader = BufReader::new(f);
+    let mut secret = String::new();
+    buf_reader.read_line(&mut secret)?;
+    Ok(secret.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn args_split_positionals_options_and_switches() {
+        let args =
+            Args::parse(words("create vacation --json --penalty 10 0.05").into_iter()).unwrap();
+        assert_eq!(args.arg(0, "command").unwrap(), "create");
+        assert_eq!(args.arg(2, "amount").unwrap(), "0.05");
+        assert_eq!(args.option("--penalty"), Some("10"));
+        assert!(args.switch("--json"));
+        assert!(!args.switch("--dry-run"));
+        assert!(args.arg(3, "extra").is_err());
+        assert!(args.required("--token").is_err());
+    }
+
+    #[test]
+    fn options_need_a_value() {
+        assert!(Args::parse(words("create --penalty").into_iter()).is_err());
+    }
+
+    #[test]
+    fn amounts_are_ether_unless_marked_wei() {
+        assert_eq!(parse_amount("0.05").unwrap(), U256::exp10(16) * 5);
+        assert_eq!(parse_amount("50000wei").unwrap(), U256::from(50_000));
+        assert_eq!(parse_amount(" 1 ").unwrap(), U256::exp10(18));
+        assert!(parse_amount("lots").is_err());
+        assert!(parse_amount("1.5wei").is_err());
+    }
+
+    #[test]
+    fn operations_map_to_timelock_calls() {
+        let vault: Address = "0x00000000000000000000000000000000000000aa"
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            parse_operation(&words("set-config min-join-fee 0.01")).unwrap(),
+            (SET_CONFIG, 0, U256::exp10(16), Address::zero())
+        );
+        assert_eq!(
+            parse_operation(&words("pause")).unwrap(),
+            (SET_PAUSED, 0, U256::one(), Address::zero())
+        );
+        assert_eq!(
+            parse_operation(&words("unpause")).unwrap(),
+            (SET_PAUSED, 0, U256::zero(), Address::zero())
+        );
+        assert_eq!(
+            parse_operation(&words(&format!("set-strategy {:?} 2500", vault))).unwrap(),
+            (SET_STRATEGY, 0, U256::from(2500), vault)
+        );
+        assert_eq!(
+            parse_operation(&words(&format!("spend-pool {:?} 0.5", Address::zero()))).unwrap(),
+            (SPEND_POOL, 0, U256::exp10(17) * 5, Address::zero())
+        );
+        assert_eq!(
+            parse_operation(&words(&format!("spend-pool {:?} 700", vault))).unwrap(),
+            (SPEND_POOL, 0, U256::from(700), vault)
+        );
+        assert_eq!(
+            parse_operation(&words("set-vault-state 2")).unwrap(),
+            (SET_VAULT_STATE, 0, U256::from(2), Address::zero())
+        );
+    }
+
+    #[test]
+    fn bad_operations_are_rejected() {
+        assert!(parse_operation(&[]).is_err());
+        assert!(parse_operation(&words("self-destruct")).is_err());
+        assert!(parse_operation(&words("set-config no-such-param 1")).is_err());
+        assert!(parse_operation(&words("transfer-admin")).is_err());
+        assert!(parse_operation(&words("upgrade not-an-address")).is_err());
+    }
+
+    #[test]
+    fn times_are_dates_or_timestamps() {
+        assert_eq!(parse_time("1970-01-02", 0).unwrap(), SECONDS_PER_DAY);
+        assert_eq!(parse_time("1970-01-02", 60).unwrap(), SECONDS_PER_DAY + 60);
+        assert_eq!(parse_time("1700000000", 60).unwrap(), 1_700_000_000);
+        assert!(parse_time("tomorrow", 0).is_err());
+    }
+
+    #[test]
+    fn salts_default_to_zero() {
+        assert_eq!(parse_salt(None).unwrap(), [0u8; 32]);
+        let salt = parse_salt(Some(&format!("0x{}", "01".repeat(32)))).unwrap();
+        assert_eq!(salt, [1u8; 32]);
+        assert!(parse_salt(Some("0x01")).is_err());
+    }
+
+    #[test]
+    fn token_amounts_use_the_token_decimals() {
+        let token: Address = "0x00000000000000000000000000000000000000aa"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            display_amount(U256::exp10(18), Address::zero(), None),
+            "1.000000000000000000 ETH"
+        );
+        assert_eq!(
+            display_amount(U256::from(1_500_000), token, Some(6)),
+            format!("1.500000 of {:?}", token)
+        );
+        assert_eq!(
+            display_amount(U256::from(1_500_000), token, None),
+            format!("1500000 of {:?}", token)
+        );
+    }
+}
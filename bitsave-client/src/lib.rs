@@ -0,0 +1,39 @@
+//! Typed client for the Bitsave Stylus program.
+//!
+//! The bindings are generated by `abigen!` from archive/abi.json, which is
+//! exported from the contract itself:
+//!
+//! ```text
+//! cargo run --example export_abi_json --features export-abi
+//! ```
+//!
+//! Every entrypoint is a method on [`Bitsave`]; reverts decode into
+//! [`revert::Revert`] and the payable calls have builders in [`payable`].
+//! [`statement`] exports a user's saving history for bookkeeping.
+
+pub mod abi;
+pub mod payable;
+pub mod revert;
+pub mod statement;
+
+use ethers::{
+    contract::{abigen, ContractError},
+    providers::Middleware,
+};
+
+use crate::revert::Revert;
+
+abigen!(Bitsave, "../archive/abi.json");
+
+/// Decodes revert data into one of the contract's `sol!` errors
+pub fn decode_revert(data: &[u8]) -> Option<BitsaveErrors> {
+    match Revert::decode(data) {
+        Revert::Custom(err) => Some(err),
+        _ => None,
+    }
+}
+
+/// The contract error behind a failed call, if the node returned revert data
+pub fn revert_of<M: Middleware>(err: &ContractError<M>) -> Option<BitsaveErrors> {
+    err.as_revert().and_then(|data| decode_revert(data))
+}
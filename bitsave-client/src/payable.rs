@@ -0,0 +1,327 @@
+//! Builders for the payable entrypoints, so the value sent is never forgotten.
+//! Every payable function in the ABI has one (see [`PAYABLE`]).
+//!
+//! ```no_run
+//! # async fn example<M: ethers::providers::Middleware>(bitsave: bitsave_client::Bitsave<M>) {
+//! use bitsave_client::payable::{CreateSaving, Join};
+//!
+//! Join::new(&bitsave, "ada").into_call().await.unwrap().send().await.unwrap();
+//! CreateSaving::new(&bitsave, "rent")
+//!     .amount(10u64.pow(17).into())
+//!     .maturity_in(30 * 24 * 60 * 60)
+//!     .penalty(5)
+//!     .into_call()
+//!     .send()
+//!     .await
+//!     .unwrap();
+//! # }
+//! ```
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::{
+    contract::{ContractCall, ContractError},
+    providers::Middleware,
+    types::{Address, Signature, U256},
+};
+
+use crate::Bitsave;
+
+/// ABI names of the payable entrypoints covered here
+pub const PAYABLE: [&str; 11] = [
+    "joinBitsave",
+    "joinBitsaveBySig",
+    "createSaving",
+    "createSavingWithTarget",
+    "createSavingBySig",
+    "incrementSaving",
+    "fund",
+    "contributeToGroup",
+    "createRosca",
+    "joinRosca",
+    "contributeRosca",
+];
+
+/// `join_bitsave`; the joining fee is read from `get_config` unless set
+pub struct Join<'a, M> {
+    bitsave: &'a Bitsave<M>,
+    user_name: String,
+    fee: Option<U256>,
+}
+
+impl<'a, M: Middleware> Join<'a, M> {
+    pub fn new(bitsave: &'a Bitsave<M>, user_name: &str) -> Self {
+        Join {
+            bitsave,
+            user_name: user_name.to_string(),
+            fee: None,
+        }
+    }
+
+    pub fn fee(mut self, fee: U256) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    pub async fn into_call(self) -> Result<ContractCall<M, Address>, ContractError<M>> {
+        let fee = match self.fee {
+            Some(fee) => fee,
+            None => self.bitsave.get_config().call().await?.2,
+        };
+
+        Ok(self
+            .bitsave
+            .join_bitsave(self.user_name.into_bytes())
+            .value(fee))
+    }
+
+    /// `join_bitsave_by_sig`: joins `user`, who signed the request; the
+    /// relayer sending the call pays the fee
+    pub async fn into_call_by_sig(
+        self,
+        user: Address,
+        deadline: U256,
+        signature: Signature,
+    ) -> Result<ContractCall<M, Address>, ContractError<M>> {
+        let fee = match self.fee {
+            Some(fee) => fee,
+            None => self.bitsave.get_config().call().await?.2,
+        };
+        let (v, r, s) = signature_parts(signature);
+
+        Ok(self
+            .bitsave
+            .join_bitsave_by_sig(user, self.user_name.into_bytes(), deadline, v, r, s)
+            .value(fee))
+    }
+}
+
+/// `create_saving` for native ETH; `amount` is sent as the call value.
+/// With a `target` it calls `create_saving_with_target` instead.
+pub struct CreateSaving<'a, M> {
+    bitsave: &'a Bitsave<M>,
+    name: String,
+    amount: U256,
+    maturity_time: U256,
+    penalty_perc: u8,
+    safe_mode: bool,
+    target: Option<(U256, bool)>,
+}
+
+impl<'a, M: Middleware> CreateSaving<'a, M> {
+    pub fn new(bitsave: &'a Bitsave<M>, name: &str) -> Self {
+        CreateSaving {
+            bitsave,
+            name: name.to_string(),
+            amount: U256::zero(),
+            maturity_time: U256::zero(),
+            penalty_perc: 0,
+            safe_mode: false,
+            target: None,
+        }
+    }
+
+    pub fn amount(mut self, amount: U256) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// Maturity as a unix timestamp
+    pub fn maturity(mut self, timestamp: u64) -> Self {
+        self.maturity_time = U256::from(timestamp);
+        self
+    }
+
+    /// Maturity `seconds` from now
+    pub fn maturity_in(self, seconds: u64) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or_default();
+        self.maturity(now + seconds)
+    }
+
+    pub fn penalty(mut self, penalty_perc: u8) -> Self {
+        self.penalty_perc = penalty_perc;
+        self
+    }
+
+    pub fn safe_mode(mut self, safe_mode: bool) -> Self {
+        self.safe_mode = safe_mode;
+        self
+    }
+
+    /// Savings goal; with `unlock_on_target` reaching it lifts the penalty
+    pub fn target(mut self, target_amount: U256, unlock_on_target: bool) -> Self {
+        self.target = Some((target_amount, unlock_on_target));
+        self
+    }
+
+    pub fn into_call(self) -> ContractCall<M, ()> {
+        let call = match self.target {
+            Some((target_amount, unlock_on_target)) => self.bitsave.create_saving_with_target(
+                self.name,
+                self.maturity_time,
+                self.penalty_perc,
+                self.safe_mode,
+                target_amount,
+                unlock_on_target,
+            ),
+            None => self.bitsave.create_saving(
+                self.name,
+                self.maturity_time,
+                self.penalty_perc,
+                self.safe_mode,
+            ),
+        };
+        call.value(self.amount)
+    }
+
+    /// `create_saving_by_sig` for `user`, who signed the request. ETH
+    /// savings send `amount` as value; token savings pull it from the
+    /// user's allowance. Targets can't be set by signature.
+    pub fn into_call_by_sig(
+        self,
+        user: Address,
+        token: Address,
+        deadline: U256,
+        signature: Signature,
+    ) -> ContractCall<M, ()> {
+        let (v, r, s) = signature_parts(signature);
+        let call = self.bitsave.create_saving_by_sig(
+            user,
+            self.name,
+            token,
+            self.amount,
+            self.maturity_time,
+            self.penalty_perc,
+            self.safe_mode,
+            deadline,
+            v,
+            r,
+            s,
+        );
+        if token.is_zero() {
+            call.value(self.amount)
+        } else {
+            call
+        }
+    }
+}
+
+/// `increment_saving`: adds `amount` of ETH to a saving
+pub fn increment_saving<M: Middleware>(
+    bitsave: &Bitsave<M>,
+    name: &str,
+    amount: U256,
+) -> ContractCall<M, ()> {
+    bitsave.increment_saving(name.to_string()).value(amount)
+}
+
+/// `fund`: tops up the general fund
+pub fn fund<M: Middleware>(bitsave: &Bitsave<M>, amount: U256) -> ContractCall<M, U256> {
+    bitsave.fund().value(amount)
+}
+
+/// `contribute_to_group`; `amount` doubles as the call value for ETH groups
+pub fn contribute_to_group<M: Middleware>(
+    bitsave: &Bitsave<M>,
+    group_id: U256,
+    amount: U256,
+    is_native: bool,
+) -> ContractCall<M, U256> {
+    let call = bitsave.contribute_to_group(group_id, amount);
+    if is_native {
+        call.value(amount)
+    } else {
+        call
+    }
+}
+
+/// `create_rosca`; the creator joins at once, posting the collateral as
+/// value for ETH circles
+pub fn create_rosca<M: Middleware>(
+    bitsave: &Bitsave<M>,
+    token: Address,
+    contribution_amount: U256,
+    collateral_amount: U256,
+    round_duration: U256,
+    penalty_perc: u8,
+    use_lottery: bool,
+) -> ContractCall<M, U256> {
+    let call = bitsave.create_rosca(
+        token,
+        contribution_amount,
+        collateral_amount,
+        round_duration,
+        penalty_perc,
+        use_lottery,
+    );
+    if token.is_zero() {
+        call.value(collateral_amount)
+    } else {
+        call
+    }
+}
+
+/// `join_rosca` / `contribute_rosca` for ETH circles send the collateral or
+/// contribution as value
+pub fn join_rosca<M: Middleware>(
+    bitsave: &Bitsave<M>,
+    rosca_id: U256,
+    collateral: U256,
+) -> ContractCall<M, ()> {
+    bitsave.join_rosca(rosca_id).value(collateral)
+}
+
+pub fn contribute_rosca<M: Middleware>(
+    bitsave: &Bitsave<M>,
+    rosca_id: U256,
+    contribution: U256,
+) -> ContractCall<M, ()> {
+    bitsave.contribute_rosca(rosca_id).value(contribution)
+}
+
+/// Splits a signature into the `(v, r, s)` the `*_by_sig` entrypoints take
+fn signature_parts(signature: Signature) -> (u8, [u8; 32], [u8; 32]) {
+    let (mut r, mut s) = ([0u8; 32], [0u8; 32]);
+    signature.r.to_big_endian(&mut r);
+    signature.s.to_big_endian(&mut s);
+    (signature.v as u8, r, s)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    #[test]
+    fn every_payable_entrypoint_has_a_builder() {
+        let abi: serde_json::Value =
+            serde_json::from_str(include_str!("../../archive/abi.json")).unwrap();
+        let payable: BTreeSet<&str> = abi
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|item| item["stateMutability"] == "payable")
+            .filter_map(|item| item["name"].as_str())
+            .collect();
+
+        assert_eq!(payable, PAYABLE.into_iter().collect());
+    }
+
+    #[test]
+    fn signature_parts_are_big_endian() {
+        let signature = Signature {
+            r: U256::from(1),
+            s: U256::from(2) << 248,
+            v: 28,
+        };
+        let (v, r, s) = signature_parts(signature);
+
+        assert_eq!(v, 28);
+        assert_eq!(r[31], 1);
+        assert_eq!(s[0], 2);
+    }
+}
@@ -0,0 +1,231 @@
+//! Decoding of Bitsave revert data.
+//!
+//! The contract reverts in three shapes: ABI-encoded `sol!` errors
+//! (`BitsaveErrors` in src/errors.rs), raw UTF-8 messages (`"...".into()`,
+//! and `BitsaveErrors::FromUtf8Error` which returns the bytes as-is), and the
+//! standard `Error(string)` / `Panic(uint256)` from calls into Solidity
+//! contracts such as tokens and strategies. [`Revert::decode`] sorts them out:
+//!
+//! ```no_run
+//! # async fn example<M: ethers::providers::Middleware>(bitsave: bitsave_client::Bitsave<M>) {
+//! use bitsave_client::revert::CallError;
+//!
+//! if let Err(err) = bitsave.withdraw_savings("rent".into()).call().await {
+//!     // "reverted: user does not exist"
+//!     println!("{}", CallError::from(err));
+//! }
+//! # }
+//! ```
+
+use std::fmt;
+
+use ethers::{
+    abi::{decode, AbiDecode, ParamType, Token},
+    contract::ContractError,
+    providers::Middleware,
+    types::{Bytes, U256},
+};
+
+use crate::BitsaveErrors;
+
+/// `Error(string)`
+const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// `Panic(uint256)`
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+/// Why a call reverted
+#[derive(Debug, Clone, PartialEq)]
+pub enum Revert {
+    /// One of the contract's `sol!` errors
+    Custom(BitsaveErrors),
+    /// Raw UTF-8 message, how most Bitsave checks fail
+    Message(String),
+    /// `Error(string)`, from `require` in a called Solidity contract
+    Reason(String),
+    /// `Panic(uint256)` with its code
+    Panic(U256),
+    /// Revert data matching none of the above
+    Unknown(Bytes),
+}
+
+impl Revert {
+    pub fn decode(data: &[u8]) -> Self {
+        if data.len() >= 4 {
+            let (selector, args) = data.split_at(4);
+            if selector == ERROR_SELECTOR {
+                if let Ok(Some(Token::String(reason))) =
+                    decode(&[ParamType::String], args).map(|tokens| tokens.into_iter().next())
+                {
+                    return Revert::Reason(reason);
+                }
+            }
+            if selector == PANIC_SELECTOR {
+                if let Ok(code) = U256::decode(args) {
+                    return Revert::Panic(code);
+                }
+            }
+        }
+
+        // abigen's `RevertString` decodes without a selector, so only the
+        // selector-checked branch above counts as `Error(string)`
+        match BitsaveErrors::decode(data) {
+            Ok(BitsaveErrors::RevertString(_)) | Err(_) => {}
+            Ok(err) => return Revert::Custom(err),
+        }
+
+        match std::str::from_utf8(data) {
+            Ok(message) if !message.is_empty() && !message.chars().any(char::is_control) => {
+                Revert::Message(message.to_string())
+            }
+            _ => Revert::Unknown(Bytes::from(data.to_vec())),
+        }
+    }
+}
+
+/// Solidity's description of a panic code
+fn panic_reason(code: U256) -> &'static str {
+    if code > U256::from(u8::MAX) {
+        return "unknown panic";
+    }
+    match code.as_u32() {
+        0x00 => "generic panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "corrupt storage byte array",
+        0x31 => "pop on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to uninitialized function",
+        _ => "unknown panic",
+    }
+}
+
+impl fmt::Display for Revert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Revert::Custom(err) => f.write_str(match err {
+                BitsaveErrors::UserNotExist(_) => "user does not exist",
+                BitsaveErrors::InvalidPrice(_) => "invalid price",
+                BitsaveErrors::GeneralError(_) => "general error",
+                BitsaveErrors::NoPendingWithdrawal(_) => "no pending withdrawal",
+                BitsaveErrors::Unauthorized(_) => "caller is not authorized",
+                BitsaveErrors::RevertString(reason) => reason,
+            }),
+            Revert::Message(message) => f.write_str(message),
+            Revert::Reason(reason) => write!(f, "{}", reason),
+            Revert::Panic(code) => write!(f, "panic 0x{:02x}: {}", code, panic_reason(*code)),
+            Revert::Unknown(data) if data.is_empty() => f.write_str("no revert data"),
+            Revert::Unknown(data) => write!(f, "undecoded revert data {}", data),
+        }
+    }
+}
+
+impl std::error::Error for Revert {}
+
+/// A failed contract call, with the revert decoded when there is one
+#[derive(Debug)]
+pub enum CallError<M: Middleware> {
+    Reverted(Revert),
+    Other(ContractError<M>),
+}
+
+impl<M: Middleware> From<ContractError<M>> for CallError<M> {
+    fn from(err: ContractError<M>) -> Self {
+        match err.as_revert() {
+            Some(data) => CallError::Reverted(Revert::decode(data)),
+            None => CallError::Other(err),
+        }
+    }
+}
+
+impl<M: Middleware> fmt::Display for CallError<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallError::Reverted(revert) => write!(f, "reverted: {}", revert),
+            CallError::Other(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl<M: Middleware> std::error::Error for CallError<M> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UserNotExist;
+    use ethers::abi::{encode, AbiEncode};
+
+    fn with_selector(selector: [u8; 4], tokens: &[Token]) -> Vec<u8> {
+        let mut data = selector.to_vec();
+        data.extend(encode(tokens));
+        data
+    }
+
+    #[test]
+    fn decodes_sol_errors() {
+        let data = BitsaveErrors::UserNotExist(UserNotExist).encode();
+        let revert = Revert::decode(&data);
+        assert_eq!(
+            revert,
+            Revert::Custom(BitsaveErrors::UserNotExist(UserNotExist))
+        );
+        assert_eq!(revert.to_string(), "user does not exist");
+    }
+
+    #[test]
+    fn decodes_raw_messages() {
+        let revert = Revert::decode(b"Reentrant call not allowed!");
+        assert_eq!(
+            revert,
+            Revert::Message("Reentrant call not allowed!".into())
+        );
+        assert_eq!(revert.to_string(), "Reentrant call not allowed!");
+    }
+
+    #[test]
+    fn decodes_error_strings() {
+        let data = with_selector(
+            ERROR_SELECTOR,
+            &[Token::String(
+                "ERC20: transfer amount exceeds balance".into(),
+            )],
+        );
+        assert_eq!(
+            Revert::decode(&data),
+            Revert::Reason("ERC20: transfer amount exceeds balance".into())
+        );
+    }
+
+    #[test]
+    fn decodes_panics() {
+        let data = with_selector(PANIC_SELECTOR, &[Token::Uint(U256::from(0x11))]);
+        let revert = Revert::decode(&data);
+        assert_eq!(revert, Revert::Panic(U256::from(0x11)));
+        assert_eq!(
+            revert.to_string(),
+            "panic 0x11: arithmetic overflow or underflow"
+        );
+    }
+
+    #[test]
+    fn keeps_unknown_bytes() {
+        let data = [0xde, 0xad, 0xbe, 0xef, 0x00];
+        assert_eq!(
+            Revert::decode(&data),
+            Revert::Unknown(Bytes::from(data.to_vec()))
+        );
+
+        // a selector that matches nothing, and a truncated `Error(string)`
+        let data = with_selector([0x12, 0x34, 0x56, 0x78], &[Token::Uint(U256::one())]);
+        assert!(matches!(Revert::decode(&data), Revert::Unknown(_)));
+        assert!(matches!(
+            Revert::decode(&ERROR_SELECTOR),
+            Revert::Unknown(_)
+        ));
+
+        assert_eq!(Revert::decode(&[]).to_string(), "no revert data");
+    }
+}
@@ -0,0 +1,485 @@
+//! Per-user account statements for bookkeeping.
+//!
+//! A statement lists every `SavingCreated`, `SavingIncremented`,
+//! `SavingWithdrawn` and `InheritanceClaimed` log of one user within a date
+//! range, with block timestamps, plus opening and closing balances per token
+//! from the user's full history. Amounts are raw token units (wei for ETH).
+//! Interest is what a saving had been credited when it was withdrawn; it is
+//! not paid out in the saving's token, so it is not part of the amount.
+//! Penalties are what an early withdrawal left behind.
+//!
+//! ```no_run
+//! # async fn example<M: ethers::providers::Middleware>(bitsave: bitsave_client::Bitsave<M>) {
+//! use bitsave_client::statement::{parse_date, Statement};
+//!
+//! let from = parse_date("2024-01-01").unwrap();
+//! let to = parse_date("2024-12-31").unwrap() + 86_399;
+//! let user = "0x0000000000000000000000000000000000000001".parse().unwrap();
+//! let statement = Statement::fetch(&bitsave, user, from, to, 0).await.unwrap();
+//! std::fs::write("statement-2024.csv", statement.to_csv()).unwrap();
+//! # }
+//! ```
+//!
+//! Savings made before the contract emitted these events do not appear.
+
+use std::collections::BTreeMap;
+
+use ethers::{
+    contract::ContractError,
+    providers::Middleware,
+    types::{Address, H256, U256},
+};
+use serde_json::{json, Value};
+
+use crate::{Bitsave, BitsaveEvents};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// A balance change of one of the user's savings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub timestamp: u64,
+    pub block_number: u64,
+    pub tx_hash: H256,
+    /// `create`, `increment`, `withdraw` or `claim`
+    pub kind: &'static str,
+    pub saving: String,
+    pub token: Address,
+    pub amount: U256,
+    pub penalty: U256,
+    /// Credited on withdrawal, not paid out
+    pub interest: U256,
+}
+
+impl Entry {
+    fn is_deposit(&self) -> bool {
+        self.kind == "create" || self.kind == "increment"
+    }
+}
+
+/// Movements in one token over the statement's range
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TokenSummary {
+    pub token: Address,
+    pub opening_balance: U256,
+    pub deposits: U256,
+    pub withdrawals: U256,
+    pub penalties: U256,
+    /// Credited on withdrawals, not paid out
+    pub interest: U256,
+    pub closing_balance: U256,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub user: Address,
+    pub from: u64,
+    pub to: u64,
+    pub entries: Vec<Entry>,
+    pub summary: Vec<TokenSummary>,
+}
+
+impl Statement {
+    /// Reads the user's logs from `from_block` on and keeps those in `from..=to`
+    pub async fn fetch<M: Middleware>(
+        bitsave: &Bitsave<M>,
+        user: Address,
+        from: u64,
+        to: u64,
+        from_block: u64,
+    ) -> Result<Self, ContractError<M>> {
+        // every tracked event has the user as its first indexed topic
+        let logs = bitsave
+            .events()
+            .from_block(from_block)
+            .topic1(H256::from(user))
+            .query_with_meta()
+            .await?;
+
+        let mut timestamps: BTreeMap<u64, u64> = BTreeMap::new();
+        let mut tokens: BTreeMap<String, Address> = BTreeMap::new();
+        let mut history = Vec::new();
+        for (event, meta) in logs {
+            let (kind, saving, token, amount, penalty, interest) = match event {
+                BitsaveEvents::SavingCreatedFilter(e) if e.user == user => {
+                    tokens.insert(e.name_of_saving.clone(), e.token_id);
+                    (
+                        "create",
+                        e.name_of_saving,
+                        e.token_id,
+                        e.amount,
+                        U256::zero(),
+                        U256::zero(),
+                    )
+                }
+                BitsaveEvents::SavingIncrementedFilter(e) if e.user == user => (
+                    "increment",
+                    e.name_of_saving,
+                    e.token_id,
+                    e.amount,
+                    U256::zero(),
+                    U256::zero(),
+                ),
+                BitsaveEvents::SavingWithdrawnFilter(e) if e.user == user => (
+                    "withdraw",
+                    e.name_of_saving,
+                    e.token_id,
+                    e.amount,
+                    e.penalty,
+                    e.interest_accumulated,
+                ),
+                BitsaveEvents::InheritanceClaimedFilter(e) if e.owner == user => {
+                    // the claim log has no token; it is the one the saving was opened in
+                    let token = tokens.get(&e.name_of_saving).copied().unwrap_or_default();
+                    (
+                        "claim",
+                        e.name_of_saving,
+                        token,
+                        e.amount,
+                        U256::zero(),
+                        U256::zero(),
+                    )
+                }
+                _ => continue,
+            };
+
+            let block_number = meta.block_number.as_u64();
+            let timestamp = match timestamps.get(&block_number) {
+                Some(timestamp) => *timestamp,
+                None => {
+                    let block = bitsave
+                        .client()
+                        .get_block(block_number)
+                        .await
+                        .map_err(ContractError::from_middleware_error)?;
+                    let timestamp = block.map(|b| b.timestamp.as_u64()).unwrap_or_default();
+                    timestamps.insert(block_number, timestamp);
+                    timestamp
+                }
+            };
+
+            history.push(Entry {
+                timestamp,
+                block_number,
+                tx_hash: meta.transaction_hash,
+                kind,
+                saving,
+                token,
+                amount,
+                penalty,
+                interest,
+            });
+        }
+
+        Ok(Self::from_history(user, from, to, history))
+    }
+
+    /// Builds the statement from the user's entries in chain order
+    pub fn from_history(user: Address, from: u64, to: u64, history: Vec<Entry>) -> Self {
+        let mut summary: BTreeMap<Address, TokenSummary> = BTreeMap::new();
+        // open balance per saving; withdrawals and claims close the saving
+        let mut balances: BTreeMap<(Address, String), U256> = BTreeMap::new();
+        let mut entries = Vec::new();
+
+        for entry in history {
+            if entry.timestamp > to {
+                break;
+            }
+            let token = summary.entry(entry.token).or_insert_with(|| TokenSummary {
+                token: entry.token,
+                ..Default::default()
+            });
+            let balance = balances
+                .entry((entry.token, entry.saving.clone()))
+                .or_default();
+
+            let in_range = entry.timestamp >= from;
+            if entry.is_deposit() {
+                *balance += entry.amount;
+                if in_range {
+                    token.deposits += entry.amount;
+                } else {
+                    token.opening_balance += entry.amount;
+                }
+            } else {
+                let closed = *balance;
+                *balance = U256::zero();
+                if in_range {
+                    token.withdrawals += entry.amount;
+                    token.penalties += entry.penalty;
+                    token.interest += entry.interest;
+                } else {
+                    token.opening_balance = token.opening_balance.saturating_sub(closed);
+                }
+            }
+
+            if in_range {
+                entries.push(entry);
+            }
+        }
+
+        for ((token, _), balance) in balances {
+            if let Some(summary) = summary.get_mut(&token) {
+                summary.closing_balance += balance;
+            }
+        }
+
+        Statement {
+            user,
+            from,
+            to,
+            entries,
+            summary: summary.into_values().collect(),
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "date,timestamp,block,tx_hash,kind,saving,token,amount,penalty,interest_credited\n",
+        );
+        for entry in &self.entries {
+            csv.push_str(&format!(
+                "{},{},{},{:?},{},{},{:?},{},{},{}\n",
+                format_timestamp(entry.timestamp),
+                entry.timestamp,
+                entry.block_number,
+                entry.tx_hash,
+                entry.kind,
+                csv_field(&entry.saving),
+                entry.token,
+                entry.amount,
+                entry.penalty,
+                entry.interest,
+            ));
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "user": format!("{:?}", self.user),
+            "from": format_timestamp(self.from),
+            "to": format_timestamp(self.to),
+            "entries": self.entries.iter().map(|entry| json!({
+                "date": format_timestamp(entry.timestamp),
+                "timestamp": entry.timestamp,
+                "block": entry.block_number,
+                "tx_hash": format!("{:?}", entry.tx_hash),
+                "kind": entry.kind,
+                "saving": entry.saving,
+                "token": format!("{:?}", entry.token),
+                "amount": entry.amount.to_string(),
+                "penalty": entry.penalty.to_string(),
+                "interest_credited": entry.interest.to_string(),
+            })).collect::<Vec<_>>(),
+            "summary": self.summary.iter().map(|token| json!({
+                "token": format!("{:?}", token.token),
+                "opening_balance": token.opening_balance.to_string(),
+                "deposits": token.deposits.to_string(),
+                "withdrawals": token.withdrawals.to_string(),
+                "penalties": token.penalties.to_string(),
+                "interest_credited": token.interest.to_string(),
+                "closing_balance": token.closing_balance.to_string(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// Quotes a CSV field when it holds a separator, quote or newline
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year as i64;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn days_in_month(year: i64, month: u64) -> u64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// `YYYY-MM-DD` to the unix timestamp of its midnight (UTC)
+pub fn parse_date(date: &str) -> Option<u64> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: u64 = parts.next()?.parse().ok()?;
+    let day: u64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    u64::try_from(days).ok().map(|days| days * SECONDS_PER_DAY)
+}
+
+/// `YYYY-MM-DD HH:MM:SS` (UTC)
+pub fn format_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / SECONDS_PER_DAY) as i64 + 719_468;
+    let seconds = timestamp % SECONDS_PER_DAY;
+
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = SECONDS_PER_DAY;
+
+    fn entry(date: &str, kind: &'static str, saving: &str, amount: u64) -> Entry {
+        Entry {
+            timestamp: parse_date(date).unwrap() + 12 * 60 * 60,
+            block_number: 0,
+            tx_hash: H256::zero(),
+            kind,
+            saving: saving.into(),
+            token: Address::zero(),
+            amount: U256::from(amount),
+            penalty: U256::zero(),
+            interest: U256::zero(),
+        }
+    }
+
+    #[test]
+    fn dates_round_trip() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2024-01-01"), Some(1_704_067_200));
+        for date in [
+            "1970-01-01",
+            "2000-02-29",
+            "2023-12-31",
+            "2024-02-29",
+            "2024-03-01",
+            "2100-02-28",
+            "2100-03-01",
+        ] {
+            let timestamp = parse_date(date).unwrap();
+            assert_eq!(format_timestamp(timestamp), format!("{} 00:00:00", date));
+            assert_eq!(
+                format_timestamp(timestamp + DAY - 1),
+                format!("{} 23:59:59", date)
+            );
+        }
+        // leap days are counted
+        assert_eq!(
+            parse_date("2024-03-01").unwrap() - parse_date("2024-02-28").unwrap(),
+            2 * DAY
+        );
+        assert_eq!(
+            parse_date("2100-03-01").unwrap() - parse_date("2100-02-28").unwrap(),
+            DAY
+        );
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        for date in [
+            "2024-02-30",
+            "2024-02-31",
+            "2023-02-29",
+            "2100-02-29",
+            "2024-04-31",
+            "2024-13-01",
+            "2024-00-10",
+            "2024-01-00",
+            "1969-12-31",
+            "2024-1",
+            "yesterday",
+        ] {
+            assert_eq!(parse_date(date), None, "{}", date);
+        }
+        assert!(parse_date("2000-02-29").is_some());
+    }
+
+    #[test]
+    fn balances_across_the_range() {
+        let from = parse_date("2024-02-01").unwrap();
+        let to = parse_date("2024-02-29").unwrap() + DAY - 1;
+        let history = vec![
+            // before the range: rent 100, school 50 then closed
+            entry("2024-01-10", "create", "rent", 100),
+            entry("2024-01-15", "create", "school", 50),
+            entry("2024-01-20", "withdraw", "school", 45),
+            // in range: rent +30 and closed, holiday opened
+            entry("2024-02-01", "increment", "rent", 30),
+            entry("2024-02-10", "withdraw", "rent", 117),
+            entry("2024-02-29", "create", "holiday", 70),
+            // after the range
+            entry("2024-03-01", "increment", "holiday", 10),
+        ];
+
+        let statement = Statement::from_history(Address::zero(), from, to, history);
+        let kinds: Vec<&str> = statement.entries.iter().map(|entry| entry.kind).collect();
+        assert_eq!(kinds, ["increment", "withdraw", "create"]);
+
+        let summary = &statement.summary[0];
+        assert_eq!(summary.opening_balance, U256::from(100));
+        assert_eq!(summary.deposits, U256::from(100));
+        assert_eq!(summary.withdrawals, U256::from(117));
+        assert_eq!(summary.closing_balance, U256::from(70));
+    }
+
+    #[test]
+    fn interest_is_reported_as_credited() {
+        let mut withdrawn = entry("2024-02-10", "withdraw", "rent", 90);
+        withdrawn.penalty = U256::from(10);
+        withdrawn.interest = U256::from(7);
+        let history = vec![entry("2024-02-01", "create", "rent", 100), withdrawn];
+
+        let statement = Statement::from_history(Address::zero(), 0, u64::MAX / 2, history);
+        let summary = &statement.summary[0];
+        // interest isn't paid out, so it adds nothing to withdrawals
+        assert_eq!(summary.withdrawals, U256::from(90));
+        assert_eq!(summary.penalties, U256::from(10));
+        assert_eq!(summary.interest, U256::from(7));
+        assert_eq!(summary.closing_balance, U256::zero());
+
+        let csv = statement.to_csv();
+        assert!(csv.starts_with(
+            "date,timestamp,block,tx_hash,kind,saving,token,amount,penalty,interest_credited\n"
+        ));
+        assert!(csv
+            .lines()
+            .nth(2)
+            .unwrap()
+            .ends_with(",withdraw,rent,0x0000000000000000000000000000000000000000,90,10,7"));
+        assert_eq!(statement.to_json()["summary"][0]["interest_credited"], "7");
+    }
+}
@@ -0,0 +1,556 @@
+//! SQLite storage for indexed Bitsave events.
+//!
+//! Every event table is append-only and keyed by `(block_number, log_index)`,
+//! so undoing a reorg is a `DELETE ... WHERE block_number > ?` per table.
+//! Amounts and maturity times are U256 and stored as decimal text; sums are
+//! done in Rust.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use bitsave_client::BitsaveEvents;
+use ethers::{
+    contract::EthLogDecode,
+    types::{Address, Log, H256, U256},
+};
+use eyre::eyre;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::{Deserialize, Serialize};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS blocks (
+    number INTEGER PRIMARY KEY,
+    hash TEXT NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS cursor (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    block_number INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS users (
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    address TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    PRIMARY KEY (block_number, log_index)
+);
+CREATE TABLE IF NOT EXISTS savings (
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    user TEXT NOT NULL,
+    name TEXT NOT NULL,
+    token TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    maturity_time TEXT NOT NULL,
+    penalty_perc INTEGER NOT NULL,
+    safe_mode INTEGER NOT NULL,
+    PRIMARY KEY (block_number, log_index)
+);
+CREATE TABLE IF NOT EXISTS movements (
+    block_number INTEGER NOT NULL,
+    log_index INTEGER NOT NULL,
+    tx_hash TEXT NOT NULL,
+    user TEXT NOT NULL,
+    name TEXT NOT NULL,
+    token TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    amount TEXT NOT NULL,
+    penalty TEXT NOT NULL,
+    interest TEXT NOT NULL,
+    PRIMARY KEY (block_number, log_index)
+);
+CREATE INDEX IF NOT EXISTS movements_user ON movements (user, name);
+CREATE INDEX IF NOT EXISTS savings_user ON savings (user, name);
+";
+
+/// Tables holding per-block rows, cleared above the fork point on a reorg
+const EVENT_TABLES: [&str; 4] = ["blocks", "users", "savings", "movements"];
+
+/// A block the indexer has seen, identified by hash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockHeader {
+    pub number: u64,
+    pub hash: H256,
+    pub timestamp: u64,
+}
+
+/// What moved a saving's balance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementKind {
+    /// `SavingCreated`, the opening deposit
+    Create,
+    /// `SavingIncremented`, by the saver or a plan
+    Increment,
+    /// `SavingWithdrawn`, net of any penalty
+    Withdraw,
+    /// `InheritanceClaimed`, paid to a beneficiary
+    Claim,
+}
+
+impl MovementKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MovementKind::Create => "create",
+            MovementKind::Increment => "increment",
+            MovementKind::Withdraw => "withdraw",
+            MovementKind::Claim => "claim",
+        }
+    }
+
+    fn parse(kind: &str) -> eyre::Result<Self> {
+        match kind {
+            "create" => Ok(MovementKind::Create),
+            "increment" => Ok(MovementKind::Increment),
+            "withdraw" => Ok(MovementKind::Withdraw),
+            "claim" => Ok(MovementKind::Claim),
+            other => Err(eyre!("Unknown movement kind {}", other)),
+        }
+    }
+
+    pub fn is_deposit(self) -> bool {
+        matches!(self, MovementKind::Create | MovementKind::Increment)
+    }
+}
+
+/// One balance change of a saving
+#[derive(Debug, Clone, PartialEq)]
+pub struct Movement {
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub tx_hash: H256,
+    pub user: Address,
+    pub name: String,
+    pub token: Address,
+    pub kind: MovementKind,
+    pub amount: U256,
+    pub penalty: U256,
+    pub interest: U256,
+}
+
+/// Aggregates over every saving in one token
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TokenTotals {
+    pub token: Address,
+    pub deposited: U256,
+    /// Paid out to savers and beneficiaries
+    pub withdrawn: U256,
+    /// Kept by the protocol on early withdrawals
+    pub penalties: U256,
+    /// Interest credited on increments
+    pub interest: U256,
+    /// Still held in open savings
+    pub locked: U256,
+    /// Users with an open saving in the token
+    pub savers: u64,
+}
+
+pub struct Database {
+    conn: Connection,
+}
+
+fn hex<T: std::fmt::Debug>(value: T) -> String {
+    format!("{:?}", value)
+}
+
+fn uint(text: &str) -> eyre::Result<U256> {
+    Ok(U256::from_dec_str(text)?)
+}
+
+impl Database {
+    pub fn open(path: impl AsRef<Path>) -> eyre::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> eyre::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> eyre::Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Database { conn })
+    }
+
+    /// Last block whose logs are fully indexed
+    pub fn cursor(&self) -> eyre::Result<Option<u64>> {
+        Ok(self
+            .conn
+            .query_row("SELECT block_number FROM cursor WHERE id = 0", [], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    pub fn block_hash(&self, number: u64) -> eyre::Result<Option<H256>> {
+        let hash: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT hash FROM blocks WHERE number = ?1",
+                params![number],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(hash.map(|hash| hash.parse()).transpose()?)
+    }
+
+    /// Indexed blocks above `number`, newest first; what a reorg can still replace
+    pub fn blocks_above(&self, number: u64) -> eyre::Result<Vec<BlockHeader>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT number, hash, timestamp FROM blocks WHERE number > ?1 ORDER BY number DESC",
+        )?;
+        let rows = stmt.query_map(params![number], |row| {
+            Ok((
+                row.get::<_, u64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, u64>(2)?,
+            ))
+        })?;
+
+        let mut blocks = Vec::new();
+        for row in rows {
+            let (number, hash, timestamp) = row?;
+            blocks.push(BlockHeader {
+                number,
+                hash: hash.parse()?,
+                timestamp,
+            });
+        }
+        Ok(blocks)
+    }
+
+    /// Applies the logs of a block range in one transaction and moves the
+    /// cursor to `through`. `blocks` must hold the header of every block with
+    /// a log and of `through` itself; a log whose block hash differs from its
+    /// header raced a reorg and fails the whole range.
+    pub fn ingest(
+        &mut self,
+        blocks: &[BlockHeader],
+        logs: &[Log],
+        through: u64,
+    ) -> eyre::Result<usize> {
+        let headers: BTreeMap<u64, &BlockHeader> = blocks.iter().map(|b| (b.number, b)).collect();
+        if !headers.contains_key(&through) {
+            return Err(eyre!("Missing header of block {}", through));
+        }
+
+        for block in blocks {
+            match self.block_hash(block.number)? {
+                Some(hash) if hash != block.hash => {
+                    return Err(eyre!(
+                        "Block {} was indexed with another hash, rewind first",
+                        block.number
+                    ))
+                }
+                _ => {}
+            }
+        }
+
+        let tx = self.conn.transaction()?;
+        for block in blocks {
+            tx.execute(
+                "INSERT OR REPLACE INTO blocks (number, hash, timestamp) VALUES (?1, ?2, ?3)",
+                params![block.number, hex(block.hash), block.timestamp],
+            )?;
+        }
+
+        let mut applied = 0;
+        for log in logs {
+            let (Some(number), Some(block_hash), Some(tx_hash), Some(log_index)) = (
+                log.block_number,
+                log.block_hash,
+                log.transaction_hash,
+                log.log_index,
+            ) else {
+                return Err(eyre!("Pending log {:?}", log));
+            };
+            let number = number.as_u64();
+            match headers.get(&number) {
+                Some(header) if header.hash == block_hash => {}
+                Some(_) => return Err(eyre!("Log of block {} is from a reorged chain", number)),
+                None => return Err(eyre!("Missing header of block {}", number)),
+            }
+
+            let Ok(event) = BitsaveEvents::decode_log(&log.clone().into()) else {
+                continue;
+            };
+            let at = (number, log_index.as_u64(), hex(tx_hash));
+            if Self::apply(&tx, at, event)? {
+                applied += 1;
+            }
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO cursor (id, block_number) VALUES (0, ?1)",
+            params![through],
+        )?;
+        tx.commit()?;
+        Ok(applied)
+    }
+
+    /// Stores one decoded event; false for events the database does not track
+    fn apply(tx: &Transaction, at: (u64, u64, String), event: BitsaveEvents) -> eyre::Result<bool> {
+        let (block_number, log_index, tx_hash) = at;
+        let movement = |user: Address,
+                        name: &str,
+                        token: Address,
+                        kind: MovementKind,
+                        amount: U256,
+                        penalty: U256,
+                        interest: U256| {
+            tx.execute(
+                "INSERT OR REPLACE INTO movements
+                 (block_number, log_index, tx_hash, user, name, token, kind, amount, penalty, interest)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    block_number,
+                    log_index,
+                    tx_hash,
+                    hex(user),
+                    name,
+                    hex(token),
+                    kind.as_str(),
+                    amount.to_string(),
+                    penalty.to_string(),
+                    interest.to_string(),
+                ],
+            )
+        };
+
+        match event {
+            BitsaveEvents::UserJoinedFilter(joined) => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO users (block_number, log_index, tx_hash, address, user_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![block_number, log_index, tx_hash, hex(joined.user), joined.user_id.to_string()],
+                )?;
+            }
+            BitsaveEvents::UserImportedFilter(imported) => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO users (block_number, log_index, tx_hash, address, user_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![block_number, log_index, tx_hash, hex(imported.user), imported.user_id.to_string()],
+                )?;
+            }
+            BitsaveEvents::SavingCreatedFilter(created) => {
+                tx.execute(
+                    "INSERT OR REPLACE INTO savings
+                     (block_number, log_index, tx_hash, user, name, token, amount, maturity_time, penalty_perc, safe_mode)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                    params![
+                        block_number,
+                        log_index,
+                        tx_hash,
+                        hex(created.user),
+                        created.name_of_saving,
+                        hex(created.token_id),
+                        created.amount.to_string(),
+                        created.maturity_time.to_string(),
+                        created.penalty_perc,
+                        created.safe_mode,
+                    ],
+                )?;
+                movement(
+                    created.user,
+                    &created.name_of_saving,
+                    created.token_id,
+                    MovementKind::Create,
+                    created.amount,
+                    U256::zero(),
+                    U256::zero(),
+                )?;
+            }
+            BitsaveEvents::SavingIncrementedFilter(incremented) => {
+                movement(
+                    incremented.user,
+                    &incremented.name_of_saving,
+                    incremented.token_id,
+                    MovementKind::Increment,
+                    incremented.amount,
+                    U256::zero(),
+                    incremented.interest,
+                )?;
+            }
+            BitsaveEvents::SavingWithdrawnFilter(withdrawn) => {
+                movement(
+                    withdrawn.user,
+                    &withdrawn.name_of_saving,
+                    withdrawn.token_id,
+                    MovementKind::Withdraw,
+                    withdrawn.amount,
+                    withdrawn.penalty,
+                    U256::zero(),
+                )?;
+            }
+            BitsaveEvents::InheritanceClaimedFilter(claimed) => {
+                // the event has no token; it is the one the saving was opened in
+                let token: Option<String> = tx
+                    .query_row(
+                        "SELECT token FROM savings WHERE user = ?1 AND name = ?2
+                         ORDER BY block_number DESC, log_index DESC LIMIT 1",
+                        params![hex(claimed.owner), claimed.name_of_saving],
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                let token = match token {
+                    Some(token) => token.parse()?,
+                    None => Address::zero(),
+                };
+                movement(
+                    claimed.owner,
+                    &claimed.name_of_saving,
+                    token,
+                    MovementKind::Claim,
+                    claimed.amount,
+                    U256::zero(),
+                    U256::zero(),
+                )?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Forgets everything above `number` and resumes from there
+    pub fn rewind(&mut self, number: u64) -> eyre::Result<()> {
+        let tx = self.conn.transaction()?;
+        for table in EVENT_TABLES {
+            let column = if table == "blocks" {
+                "number"
+            } else {
+                "block_number"
+            };
+            tx.execute(
+                &format!("DELETE FROM {} WHERE {} > ?1", table, column),
+                params![number],
+            )?;
+        }
+        tx.execute(
+            "UPDATE cursor SET block_number = ?1 WHERE id = 0 AND block_number > ?1",
+            params![number],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Balance changes in block order, optionally for one user and a timestamp range
+    pub fn movements(
+        &self,
+        user: Option<Address>,
+        from_timestamp: u64,
+        to_timestamp: u64,
+    ) -> eyre::Result<Vec<Movement>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.block_number, b.timestamp, m.tx_hash, m.user, m.name, m.token, m.kind,
+                    m.amount, m.penalty, m.interest
+             FROM movements m JOIN blocks b ON b.number = m.block_number
+             WHERE (?1 IS NULL OR m.user = ?1) AND b.timestamp >= ?2 AND b.timestamp <= ?3
+             ORDER BY m.block_number, m.log_index",
+        )?;
+        let rows = stmt.query_map(
+            params![
+                user.map(hex),
+                i64::try_from(from_timestamp).unwrap_or(i64::MAX),
+                i64::try_from(to_timestamp).unwrap_or(i64::MAX)
+            ],
+            |row| {
+                Ok((
+                    row.get::<_, u64>(0)?,
+                    row.get::<_, u64>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, String>(5)?,
+                    row.get::<_, String>(6)?,
+                    row.get::<_, String>(7)?,
+                    row.get::<_, String>(8)?,
+                    row.get::<_, String>(9)?,
+                ))
+            },
+        )?;
+
+        let mut movements = Vec::new();
+        for row in rows {
+            let (
+                block_number,
+                timestamp,
+                tx_hash,
+                user,
+                name,
+                token,
+                kind,
+                amount,
+                penalty,
+                interest,
+            ) = row?;
+            movements.push(Movement {
+                block_number,
+                timestamp,
+                tx_hash: tx_hash.parse()?,
+                user: user.parse()?,
+                name,
+                token: token.parse()?,
+                kind: MovementKind::parse(&kind)?,
+                amount: uint(&amount)?,
+                penalty: uint(&penalty)?,
+                interest: uint(&interest)?,
+            });
+        }
+        Ok(movements)
+    }
+
+    /// Deposits, payouts, penalties and open balances per token
+    pub fn token_totals(&self) -> eyre::Result<Vec<TokenTotals>> {
+        let mut totals: BTreeMap<Address, TokenTotals> = BTreeMap::new();
+        // open balance per (user, saving), to count savers with funds locked
+        let mut open: BTreeMap<(Address, Address, String), U256> = BTreeMap::new();
+
+        for movement in self.movements(None, 0, u64::MAX)? {
+            let entry = totals.entry(movement.token).or_insert_with(|| TokenTotals {
+                token: movement.token,
+                ..Default::default()
+            });
+            let balance = open
+                .entry((movement.token, movement.user, movement.name.clone()))
+                .or_default();
+
+            if movement.kind.is_deposit() {
+                entry.deposited += movement.amount;
+                entry.interest += movement.interest;
+                *balance += movement.amount;
+            } else {
+                entry.withdrawn += movement.amount;
+                entry.penalties += movement.penalty;
+                // withdrawals and claims close the saving
+                *balance = U256::zero();
+            }
+        }
+
+        let mut savers = BTreeSet::new();
+        for ((token, user, _), balance) in open {
+            if balance.is_zero() {
+                continue;
+            }
+            if let Some(entry) = totals.get_mut(&token) {
+                entry.locked += balance;
+                if savers.insert((token, user)) {
+                    entry.savers += 1;
+                }
+            }
+        }
+
+        Ok(totals.into_values().collect())
+    }
+
+    /// Totals for one token, zeroed if it was never saved
+    pub fn totals_for(&self, token: Address) -> eyre::Result<TokenTotals> {
+        Ok(self
+            .token_totals()?
+            .into_iter()
+            .find(|totals| totals.token == token)
+            .unwrap_or(TokenTotals {
+                token,
+                ..Default::default()
+            }))
+    }
+}
@@ -0,0 +1,135 @@
+//! Follows a deployment's logs into a [`Database`].
+//!
+//! Logs are fetched in ranges; the headers of blocks with logs, and of the
+//! range end, are stored with their hashes. Before each range the stored
+//! hashes within `reorg_depth` of the head are compared with the chain, and
+//! the database is rewound to the newest block that still matches.
+
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Address, Filter},
+};
+use eyre::eyre;
+
+use crate::db::{BlockHeader, Database};
+
+pub struct Follower {
+    provider: Arc<Provider<Http>>,
+    address: Address,
+    /// First block to index when the database is empty
+    pub from_block: u64,
+    /// Blocks per `eth_getLogs` range
+    pub batch_size: u64,
+    /// Blocks left unindexed behind the head
+    pub confirmations: u64,
+    /// How far below the head indexed blocks are re-checked for reorgs
+    pub reorg_depth: u64,
+}
+
+impl Follower {
+    pub fn new(provider: Arc<Provider<Http>>, address: Address) -> Self {
+        Follower {
+            provider,
+            address,
+            from_block: 0,
+            batch_size: 1000,
+            confirmations: 0,
+            reorg_depth: 64,
+        }
+    }
+
+    /// The canonical header at `number`, if the chain is that long
+    async fn header(&self, number: u64) -> eyre::Result<Option<BlockHeader>> {
+        let Some(block) = self.provider.get_block(number).await? else {
+            return Ok(None);
+        };
+        let hash = block
+            .hash
+            .ok_or_else(|| eyre!("Block {} has no hash", number))?;
+        Ok(Some(BlockHeader {
+            number,
+            hash,
+            timestamp: block.timestamp.as_u64(),
+        }))
+    }
+
+    /// Rewinds past indexed blocks that left the canonical chain; returns
+    /// the fork point if there was a reorg
+    pub async fn check_reorg(&self, db: &mut Database, head: u64) -> eyre::Result<Option<u64>> {
+        let mut fork = None;
+        for block in db.blocks_above(head.saturating_sub(self.reorg_depth))? {
+            let canonical = self.header(block.number).await?;
+            if canonical.map(|header| header.hash) == Some(block.hash) {
+                break;
+            }
+            fork = Some(block.number.saturating_sub(1));
+        }
+
+        if let Some(number) = fork {
+            db.rewind(number)?;
+        }
+        Ok(fork)
+    }
+
+    /// Indexes the next range; returns its last block, or `None` when caught up
+    pub async fn step(&self, db: &mut Database) -> eyre::Result<Option<u64>> {
+        let head = self.provider.get_block_number().await?.as_u64();
+        if let Some(fork) = self.check_reorg(db, head).await? {
+            println!("reorg: rewound to block {}", fork);
+        }
+
+        let safe_head = head.saturating_sub(self.confirmations);
+        let from = match db.cursor()? {
+            Some(cursor) => cursor + 1,
+            None => self.from_block,
+        };
+        if from > safe_head {
+            return Ok(None);
+        }
+        let to = (from + self.batch_size - 1).min(safe_head);
+
+        let filter = Filter::new()
+            .address(self.address)
+            .from_block(from)
+            .to_block(to);
+        let logs = self.provider.get_logs(&filter).await?;
+
+        let mut numbers: BTreeSet<u64> = logs
+            .iter()
+            .filter_map(|log| log.block_number)
+            .map(|number| number.as_u64())
+            .collect();
+        numbers.insert(to);
+
+        let mut blocks = Vec::new();
+        for number in numbers {
+            let header = self
+                .header(number)
+                .await?
+                .ok_or_else(|| eyre!("Block {} disappeared", number))?;
+            blocks.push(header);
+        }
+
+        // a log from an orphaned block fails here and the range is retried
+        db.ingest(&blocks, &logs, to)?;
+        Ok(Some(to))
+    }
+
+    /// Follows the chain until stopped, polling once caught up
+    pub async fn run(&self, db: &mut Database, poll_interval: Duration) -> eyre::Result<()> {
+        loop {
+            match self.step(db).await {
+                Ok(Some(to)) => println!("indexed through block {}", to),
+                Ok(None) => tokio::time::sleep(poll_interval).await,
+                Err(err) => {
+                    eprintln!("{}, retrying", err);
+                    tokio::time::sleep(poll_interval).await;
+                }
+            }
+        }
+    }
+}
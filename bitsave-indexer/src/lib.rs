@@ -0,0 +1,18 @@
+//! Off-chain savings database built from Bitsave logs.
+//!
+//! [`follow::Follower`] reads `UserJoined`, `UserImported`, `SavingCreated`,
+//! `SavingIncremented`, `SavingWithdrawn` and `InheritanceClaimed` logs from
+//! a deployment and [`db::Database`] stores them in SQLite, rewinding by
+//! block hash on reorgs. [`db::Database::token_totals`] answers the
+//! dashboard queries; [`db::Database::ingest`] takes recorded logs directly,
+//! which is how the fixture tests drive it.
+//!
+//! Savings written by `importSavings` and accounts moved by
+//! `executeRecovery` emit no saving events, so their balances are missing
+//! from (or stay under the old address in) the database.
+
+pub mod db;
+pub mod follow;
+
+pub use db::{BlockHeader, Database, Movement, MovementKind, TokenTotals};
+pub use follow::Follower;
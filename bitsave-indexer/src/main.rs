@@ -0,0 +1,114 @@
+//! Indexes a Bitsave deployment into SQLite, or prints per-token totals.
+//!
+//! ```text
+//! bitsave-indexer [--db FILE] [--from-block N] [--batch-size N]
+//!     [--confirmations N] [--reorg-depth N] [--poll SECONDS] [--once]
+//! bitsave-indexer totals [--db FILE] [--token ADDRESS]
+//! ```
+//!
+//! Reads `RPC_URL` and `STYLUS_PROGRAM_ADDRESS` from the environment or `.env`.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use bitsave_indexer::{Database, Follower};
+use dotenv::dotenv;
+use ethers::{
+    providers::{Http, Provider},
+    types::Address,
+};
+use eyre::eyre;
+
+/// Stylus RPC endpoint url.
+const RPC_URL: &str = "RPC_URL";
+
+/// Deployed program address.
+const STYLUS_PROGRAM_ADDRESS: &str = "STYLUS_PROGRAM_ADDRESS";
+
+struct Options {
+    totals: bool,
+    db: String,
+    token: Option<Address>,
+    from_block: u64,
+    batch_size: u64,
+    confirmations: u64,
+    reorg_depth: u64,
+    poll: u64,
+    once: bool,
+}
+
+impl Options {
+    fn parse() -> eyre::Result<Self> {
+        let mut options = Options {
+            totals: false,
+            db: "bitsave.db".to_string(),
+            token: None,
+            from_block: 0,
+            batch_size: 1000,
+            confirmations: 0,
+            reorg_depth: 64,
+            poll: 5,
+            once: false,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| eyre!("{} needs a value", arg));
+            match arg.as_str() {
+                "totals" => options.totals = true,
+                "--db" => options.db = value()?,
+                "--token" => options.token = Some(value()?.parse()?),
+                "--from-block" => options.from_block = value()?.parse()?,
+                "--batch-size" => options.batch_size = value()?.parse()?,
+                "--confirmations" => options.confirmations = value()?.parse()?,
+                "--reorg-depth" => options.reorg_depth = value()?.parse()?,
+                "--poll" => options.poll = value()?.parse()?,
+                "--once" => options.once = true,
+                _ => return Err(eyre!("Unknown argument {}", arg)),
+            }
+        }
+
+        if options.batch_size == 0 {
+            return Err(eyre!("--batch-size must be positive"));
+        }
+        Ok(options)
+    }
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    dotenv().ok();
+    let options = Options::parse()?;
+    let mut db = Database::open(&options.db)?;
+
+    if options.totals {
+        let totals = match options.token {
+            Some(token) => vec![db.totals_for(token)?],
+            None => db.token_totals()?,
+        };
+        println!("{}", serde_json::to_string_pretty(&totals)?);
+        return Ok(());
+    }
+
+    let rpc_url = std::env::var(RPC_URL).map_err(|_| eyre!("No {} env var set", RPC_URL))?;
+    let address: Address = std::env::var(STYLUS_PROGRAM_ADDRESS)
+        .map_err(|_| eyre!("No {} env var set", STYLUS_PROGRAM_ADDRESS))?
+        .parse()?;
+    let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
+
+    let mut follower = Follower::new(provider, address);
+    follower.from_block = options.from_block;
+    follower.batch_size = options.batch_size;
+    follower.confirmations = options.confirmations;
+    follower.reorg_depth = options.reorg_depth;
+
+    if options.once {
+        while let Some(to) = follower.step(&mut db).await? {
+            println!("indexed through block {}", to);
+        }
+        return Ok(());
+    }
+    follower
+        .run(&mut db, Duration::from_secs(options.poll))
+        .await
+}
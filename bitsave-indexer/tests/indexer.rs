@@ -0,0 +1,155 @@
+//! Drives the database with recorded logs from tests/fixtures.
+//!
+//! savings.json: alice and bob join (block 100), alice opens `rent` with
+//! 1 ETH (101) and adds 0.5 ETH (102), bob opens `school` with 100 USDC (103),
+//! alice withdraws `rent` early for 1.35 ETH after a 0.15 ETH penalty (104)
+//! and opens `holiday` with 2 ETH (105). reorg.json replaces 104 and 105 with
+//! a fork where bob adds 50 USDC instead.
+
+use bitsave_indexer::{BlockHeader, Database, MovementKind};
+use ethers::{
+    types::{Address, Log, U256},
+    utils::parse_ether,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Fixture {
+    through: u64,
+    blocks: Vec<BlockHeader>,
+    logs: Vec<Log>,
+}
+
+fn fixture(name: &str) -> Fixture {
+    let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name);
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn ingest(db: &mut Database, name: &str) -> eyre::Result<usize> {
+    let fixture = fixture(name);
+    db.ingest(&fixture.blocks, &fixture.logs, fixture.through)
+}
+
+fn ether(amount: &str) -> U256 {
+    parse_ether(amount).unwrap()
+}
+
+fn usdc() -> Address {
+    Address::from_low_u64_be(0x05dc)
+}
+
+#[test]
+fn totals_per_token() {
+    let mut db = Database::in_memory().unwrap();
+    // the PauseUpdated log is skipped
+    assert_eq!(ingest(&mut db, "savings.json").unwrap(), 7);
+    assert_eq!(db.cursor().unwrap(), Some(106));
+
+    let eth = db.totals_for(Address::zero()).unwrap();
+    assert_eq!(eth.deposited, ether("3.5"));
+    assert_eq!(eth.withdrawn, ether("1.35"));
+    assert_eq!(eth.penalties, ether("0.15"));
+    assert_eq!(eth.interest, U256::from(3));
+    assert_eq!(eth.locked, ether("2"));
+    assert_eq!(eth.savers, 1);
+
+    let usdc = db.totals_for(usdc()).unwrap();
+    assert_eq!(usdc.deposited, U256::from(100_000_000));
+    assert_eq!(usdc.locked, U256::from(100_000_000));
+    assert_eq!(usdc.savers, 1);
+
+    assert_eq!(db.token_totals().unwrap().len(), 2);
+}
+
+#[test]
+fn movements_in_block_order() {
+    let mut db = Database::in_memory().unwrap();
+    ingest(&mut db, "savings.json").unwrap();
+
+    let alice = Address::from_low_u64_be(0xa11ce);
+    let kinds: Vec<MovementKind> = db
+        .movements(Some(alice), 0, u64::MAX)
+        .unwrap()
+        .iter()
+        .map(|movement| movement.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            MovementKind::Create,
+            MovementKind::Increment,
+            MovementKind::Withdraw,
+            MovementKind::Create
+        ]
+    );
+
+    // blocks 101 and 102 only
+    let in_range = db
+        .movements(Some(alice), 1_700_086_400, 1_700_172_800)
+        .unwrap();
+    assert_eq!(in_range.len(), 2);
+}
+
+#[test]
+fn reorg_is_rewound_by_block_hash() {
+    let mut db = Database::in_memory().unwrap();
+    ingest(&mut db, "savings.json").unwrap();
+
+    // the fork's blocks conflict with indexed hashes until rewound
+    assert!(ingest(&mut db, "reorg.json").is_err());
+    let fork = fixture("reorg.json");
+    assert_ne!(db.block_hash(104).unwrap(), Some(fork.blocks[0].hash));
+
+    db.rewind(103).unwrap();
+    assert_eq!(db.cursor().unwrap(), Some(103));
+    assert_eq!(db.blocks_above(103).unwrap(), Vec::new());
+
+    ingest(&mut db, "reorg.json").unwrap();
+    assert_eq!(db.cursor().unwrap(), Some(105));
+
+    let eth = db.totals_for(Address::zero()).unwrap();
+    assert_eq!(eth.deposited, ether("1.5"));
+    assert_eq!(eth.withdrawn, U256::zero());
+    assert_eq!(eth.locked, ether("1.5"));
+
+    let usdc = db.totals_for(usdc()).unwrap();
+    assert_eq!(usdc.deposited, U256::from(150_000_000));
+    assert_eq!(usdc.interest, U256::from(1));
+}
+
+#[test]
+fn logs_from_orphaned_blocks_are_rejected() {
+    let mut db = Database::in_memory().unwrap();
+    let mut recorded = fixture("savings.json");
+    recorded.blocks[1].hash = fixture("reorg.json").blocks[0].hash;
+
+    assert!(db
+        .ingest(&recorded.blocks, &recorded.logs, recorded.through)
+        .is_err());
+    assert_eq!(db.cursor().unwrap(), None);
+    assert_eq!(db.token_totals().unwrap(), Vec::new());
+}
+
+#[test]
+fn maturity_times_past_u64_are_kept() {
+    let mut db = Database::in_memory().unwrap();
+    let mut recorded = fixture("savings.json");
+
+    // alice's `rent` (block 101) matures at type(uint256).max; after the
+    // string offset, token and amount words
+    let created = recorded
+        .logs
+        .iter_mut()
+        .find(|log| log.block_number == Some(101.into()))
+        .unwrap();
+    let mut data = created.data.to_vec();
+    data[96..128].fill(0xff);
+    created.data = data.into();
+
+    assert_eq!(
+        db.ingest(&recorded.blocks, &recorded.logs, recorded.through)
+            .unwrap(),
+        7
+    );
+    assert_eq!(db.totals_for(Address::zero()).unwrap().locked, ether("2"));
+}
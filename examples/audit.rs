@@ -0,0 +1,268 @@
+//! Audits a Bitsave deployment's books against its token balances.
+//!
+//! Savings are summed per token over every user (from `UserJoined` and
+//! `UserImported` logs, or a `--users` file), group savings and ROSCA round
+//! pots over every id, and the totals are compared with the contract's ledger
+//! (`getTokenLedger`) and with what it actually holds. `checkInvariants`
+//! gives the contract's own view.
+//!
+//! ```
+//! cargo run --example audit -- [--from-block N] [--users FILE] [--token ADDRESS]...
+//! ```
+//!
+//! Native ETH is always audited. ROSCA collateral has no getter, so circles
+//! are only checked through the ledger's pooled total.
+
+use bitsave_client::{Bitsave, UserImportedFilter, UserJoinedFilter};
+use dotenv::dotenv;
+use ethers::{
+    contract::EthEvent,
+    prelude::abigen,
+    providers::{Http, Middleware, Provider},
+    types::{Address, Filter, U256},
+};
+use eyre::eyre;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+/// Stylus RPC endpoint url.
+const RPC_URL: &str = "RPC_URL";
+
+/// Deployment to audit.
+const STYLUS_PROGRAM_ADDRESS: &str = "STYLUS_PROGRAM_ADDRESS";
+
+abigen!(
+    Erc20,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+    ]"#
+);
+
+struct Options {
+    from_block: u64,
+    users_file: Option<String>,
+    tokens: Vec<Address>,
+}
+
+impl Options {
+    fn parse() -> eyre::Result<Self> {
+        let mut options = Options {
+            from_block: 0,
+            users_file: None,
+            tokens: vec![Address::zero()],
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| eyre!("{} needs a value", arg));
+            match arg.as_str() {
+                "--from-block" => options.from_block = value()?.parse()?,
+                "--users" => options.users_file = Some(value()?),
+                "--token" => {
+                    let token = value()?.parse()?;
+                    if !options.tokens.contains(&token) {
+                        options.tokens.push(token);
+                    }
+                }
+                _ => return Err(eyre!("Unknown argument {}", arg)),
+            }
+        }
+        Ok(options)
+    }
+}
+
+/// Books recomputed from per-account views
+#[derive(Debug, Default)]
+struct Totals {
+    principal: U256,
+    savings: u64,
+    pooled: U256,
+}
+
+async fn list_users(
+    provider: &Provider<Http>,
+    address: Address,
+    options: &Options,
+) -> eyre::Result<Vec<Address>> {
+    let mut users = Vec::new();
+    if let Some(path) = &options.users_file {
+        let file = std::fs::File::open(path)?;
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                users.push(line.parse()?);
+            }
+        }
+    }
+
+    let filter = Filter::new()
+        .address(address)
+        .topic0(vec![
+            UserJoinedFilter::signature(),
+            UserImportedFilter::signature(),
+        ])
+        .from_block(options.from_block);
+    for log in provider.get_logs(&filter).await? {
+        let user = Address::from(log.topics[1]);
+        if !users.contains(&user) {
+            users.push(user);
+        }
+    }
+
+    Ok(users)
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    dotenv().ok();
+    let options = Options::parse()?;
+
+    let rpc_url = std::env::var(RPC_URL).map_err(|_| eyre!("No {} env var set", RPC_URL))?;
+    let address: Address = std::env::var(STYLUS_PROGRAM_ADDRESS)
+        .map_err(|_| eyre!("No {} env var set", STYLUS_PROGRAM_ADDRESS))?
+        .parse()?;
+
+    let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
+    let bitsave = Bitsave::new(address, provider.clone());
+
+    let mut totals: BTreeMap<Address, Totals> = BTreeMap::new();
+
+    let users = list_users(&provider, address, &options).await?;
+    println!("{} users found", users.len());
+    for user in users {
+        for name in bitsave.get_savings_names(user).call().await? {
+            let (is_valid, amount, _, _, token_id, _, _, _) =
+                bitsave.get_saving(user, name).call().await?;
+            if is_valid {
+                let total = totals.entry(token_id).or_default();
+                total.principal += amount;
+                total.savings += 1;
+            }
+        }
+    }
+
+    // ids are handed out from 1; an empty creator is past the last group
+    let mut group_id = U256::one();
+    loop {
+        let (creator, token_id, _, _, contributed, withdrawn, _) =
+            bitsave.get_group(group_id).call().await?;
+        if creator.is_zero() {
+            break;
+        }
+        totals.entry(token_id).or_default().pooled += contributed.saturating_sub(withdrawn);
+        group_id += U256::one();
+    }
+
+    let mut rosca_id = U256::one();
+    loop {
+        let (token_id, _, member_count, _, _, round_pot, _) =
+            bitsave.get_rosca(rosca_id).call().await?;
+        if member_count.is_zero() {
+            break;
+        }
+        totals.entry(token_id).or_default().pooled += round_pot;
+        rosca_id += U256::one();
+    }
+
+    println!(
+        "pools: general fund {}, accumulated {}, tokens {}",
+        bitsave.get_bitsave_balance().call().await?,
+        bitsave.get_accumulated_pool().call().await?,
+        bitsave.get_tokens_balance().call().await?
+    );
+
+    let mut problems = 0;
+    for token_id in &options.tokens {
+        let books = totals.remove(token_id).unwrap_or_default();
+        audit_token(
+            &provider,
+            &bitsave,
+            address,
+            *token_id,
+            &books,
+            &mut problems,
+        )
+        .await?;
+    }
+    // tokens found in savings but not asked for
+    for (token_id, books) in &totals {
+        audit_token(
+            &provider,
+            &bitsave,
+            address,
+            *token_id,
+            books,
+            &mut problems,
+        )
+        .await?;
+    }
+
+    if problems > 0 {
+        return Err(eyre!("{} problems found", problems));
+    }
+    println!("books balance");
+    Ok(())
+}
+
+async fn audit_token(
+    provider: &Arc<Provider<Http>>,
+    bitsave: &Bitsave<Provider<Http>>,
+    address: Address,
+    token_id: Address,
+    books: &Totals,
+    problems: &mut usize,
+) -> eyre::Result<()> {
+    let (principal, pooled, pending) = bitsave.get_token_ledger(token_id).call().await?;
+    let (_, active_savings, penalties, interest_credited, _) =
+        bitsave.get_protocol_stats(token_id).call().await?;
+    let held = if token_id.is_zero() {
+        provider.get_balance(address, None).await?
+    } else {
+        Erc20::new(token_id, provider.clone())
+            .balance_of(address)
+            .call()
+            .await?
+    };
+
+    println!("{:?}:", token_id);
+    println!(
+        "  savings   {} across {} savings, ledger {}",
+        books.principal, books.savings, principal
+    );
+    println!(
+        "  pooled    groups and round pots {}, ledger {}",
+        books.pooled, pooled
+    );
+    println!("  pending   {}", pending);
+    println!(
+        "  stats     {} active savings, {} penalties, {} interest credited",
+        active_savings, penalties, interest_credited
+    );
+    println!("  held      {}", held);
+
+    if books.principal != principal {
+        *problems += 1;
+        println!("  ! ledger principal differs from the savings");
+    }
+    if U256::from(books.savings) != active_savings {
+        *problems += 1;
+        println!("  ! ledger counts a different number of savings");
+    }
+    if books.pooled > pooled {
+        *problems += 1;
+        println!("  ! ledger pooled is below the groups and round pots");
+    }
+
+    let (solvent, balance, liabilities, shortfall) =
+        bitsave.check_invariants(token_id).call().await?;
+    if !solvent {
+        *problems += 1;
+        println!(
+            "  ! insolvent: holds {} against {}, short {}",
+            balance, liabilities, shortfall
+        );
+    }
+    Ok(())
+}
@@ -4,12 +4,12 @@
 //! but with Stylus, it is accessible just as a normal Solidity smart contract is via an ABI.
 
 use alloy_primitives::U256;
+use bitsave_client::{revert::CallError, Bitsave};
 use dotenv::dotenv;
 use ethers::{
     abi::{decode, AbiEncode, ParamType},
     contract::ContractError,
     middleware::SignerMiddleware,
-    prelude::abigen,
     providers::{Http, Middleware, Provider},
     signers::{LocalWallet, Signer},
     types::{Address, Bytes},
@@ -41,23 +41,6 @@ async fn main() -> eyre::Result<()> {
     let program_address = std::env::var(STYLUS_PROGRAM_ADDRESS)
         .map_err(|_| eyre!("No {} env var set", STYLUS_PROGRAM_ADDRESS))?;
 
-    abigen!(
-        Bitsave,
-        r#"[
-            function getBitsaveUserCount() external view returns (uint256)
-            function getUserDetails(uint8[] memory username) external view returns (string memory, uint256, address)
-            function getBitsaveBalance() external view returns (uint256)
-            function getAccumulatedPool() external view returns (uint256)
-            function getTokensBalance() external view returns (uint256)
-
-            function joinBitsave(uint8[] calldata user_name) external payable returns (address)
-            function fund() external payable returns (uint256)
-            function createSaving(string calldata name_of_saving, uint256 maturity_time, uint8 penalty_perc, bool use_safe_mode) external
-            function incrementSaving(string calldata name_of_saving) external
-            function withdrawSavings(string calldata name_of_saving) external returns (uint256)
-        ]"#
-    );
-
     let provider = Provider::<Http>::try_from(rpc_url)?;
     let address: Address = program_address.parse()?;
 
@@ -86,40 +69,27 @@ async fn main() -> eyre::Result<()> {
     // println!("Join bitsave return value = {:?}", join_res);
 
     println!("{:?}", "xpan".as_bytes().to_vec());
-    let user_data = bitsave
-        .get_user_details("xpan".as_bytes().to_vec())
-        .call()
-        .await;
-    println!("{:?}", user_data);
-    if let Ok((username, user_id, user_address)) = user_data {
-        println!(
+    let user_data = bitsave.get_user_details().call().await;
+    match user_data {
+        Ok((username, user_id, user_address)) => println!(
             "User details\nName: {:?}\nId: {user_id}\nAddress: {user_address}",
-            String::from_utf8(username.into()).unwrap()
-        );
-    } else {
-        println!("Err decoding {:?}", user_data);
+            String::from_utf8(username).unwrap()
+        ),
+        Err(err) => println!("User details: {}", CallError::from(err)),
     }
 
-    // if let Err(ContractErr   or::Revert(Bytes(join_val))) = join_res {
-    //     println!("{:?}", String::from_utf8(join_val.encode()));
-    // };
-
     let count_res = bitsave.get_bitsave_user_count().call().await;
     println!("Bitsave user count = {:?}", count_res);
 
-    // let create_res = bitsave
-    //     .create_saving("schoolFee".to_string(), 1714242866.into(), 2, false)
-    //     .call()
-    //     .await;
-    // println!("Create saving bitsave return value = {:?}", create_res);
-    //
-    // if let Err(err_vec) = create_res {
-    //     println!("{:#?}", err_vec);
-    // }
-    // if let Err(ContractError::Revert(Bytes(err_vec))) = create_res {
-    //     let err = err_vec.to_vec();
-    //     println!("{:?}", String::from_utf8(err));
-    // }
+    // reverts mix `sol!` errors and raw strings; `CallError` decodes both
+    let create_res = bitsave
+        .create_saving("schoolFee".to_string(), 1714242866.into(), 2, false)
+        .call()
+        .await;
+    match create_res {
+        Ok(()) => println!("Create saving would succeed"),
+        Err(err) => println!("Create saving: {}", CallError::from(err)),
+    }
 
     Ok(())
 }
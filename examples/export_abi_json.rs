@@ -0,0 +1,41 @@
+//! Regenerates archive/abi.json from the contract: the Solidity interface the
+//! Stylus SDK exports, plus the events and errors declared with `sol!`.
+//!
+//! ```
+//! cargo run --example export_abi_json --features export-abi
+//! ```
+
+use std::fmt;
+
+use stylus_hello_world::Bitsave;
+use stylus_sdk::abi::export::GenerateAbi;
+
+struct Interface;
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Bitsave::fmt_abi(f)
+    }
+}
+
+fn main() -> eyre::Result<()> {
+    let abi = bitsave_client::abi::from_sources(
+        &Interface.to_string(),
+        &[
+            include_str!("../src/events.rs"),
+            include_str!("../src/errors.rs"),
+        ],
+    )
+    .map_err(|err| eyre::eyre!(err))?;
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/archive/abi.json");
+    std::fs::write(path, bitsave_client::abi::to_json(&abi))?;
+    println!(
+        "wrote {} functions, {} events, {} errors to {}",
+        abi.functions().count(),
+        abi.events().count(),
+        abi.errors().count(),
+        path
+    );
+    Ok(())
+}
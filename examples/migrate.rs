@@ -0,0 +1,462 @@
+//! Migrates users and savings from one Bitsave deployment to another, or to a
+//! new storage layout behind the same proxy.
+//!
+//! Accounts are read straight from the source's storage (`sol_storage!` lays
+//! fields out like Solidity), so no getters are needed on the old version.
+//! Users come from `UserJoined` and `UserImported` logs, or from a `--users`
+//! file for deployments that predate the events. The target receives batched
+//! `importUsers` / `importSavings` calls while its migration window is open
+//! (a governance `OPEN_MIGRATION` operation), then the admin calls
+//! `closeMigration`.
+//!
+//! ```
+//! cargo run --example migrate -- [--dry-run] [--from-block N] [--source-block N]
+//!     [--users FILE] [--names FILE] [--batch-size N]
+//! ```
+//!
+//! `--dry-run` only prints what would change on the target; point `RPC_URL`
+//! at a local devnode to rehearse a migration. `--names` takes `address,name`
+//! lines for savings that older versions did not list in `savings_names`.
+//!
+//! Only the core saving fields are carried; plans, targets, beneficiaries and
+//! receipts have to be set up again on the new layout.
+
+use dotenv::dotenv;
+use ethers::{
+    middleware::SignerMiddleware,
+    prelude::abigen,
+    providers::{Http, Middleware, Provider},
+    signers::{LocalWallet, Signer},
+    types::{Address, BlockId, BlockNumber, Filter, H256, U256},
+    utils::keccak256,
+};
+use eyre::eyre;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Your private key file path.
+const PRIV_KEY_PATH: &str = "PRIV_KEY_PATH";
+
+/// Stylus RPC endpoint url.
+const RPC_URL: &str = "RPC_URL";
+
+/// Deployment to read accounts from.
+const STYLUS_PROGRAM_ADDRESS: &str = "STYLUS_PROGRAM_ADDRESS";
+
+/// Deployment to write accounts to; the source itself for in-place migrations.
+const MIGRATION_TARGET_ADDRESS: &str = "MIGRATION_TARGET_ADDRESS";
+
+/// Slot of `users_mapping` in `Bitsave`
+const USERS_MAPPING_SLOT: u64 = 4;
+
+abigen!(
+    BitsaveMigration,
+    r#"[
+        function isMigrationOpen() external view returns (bool)
+        function importUsers(address[] memory users, uint256[] memory user_ids, string[] memory user_names) external returns (uint256)
+        function importSavings(address user, string[] memory names, address[] memory token_ids, uint256[] memory amounts, uint256[] memory maturity_times, uint256[] memory start_times, uint256[] memory interests, uint8[] memory penalty_percs, bool[] memory safe_modes) external
+        event UserJoined(address indexed user, uint256 user_id)
+    ]"#
+);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Saving {
+    name: String,
+    is_valid: bool,
+    amount: U256,
+    maturity_time: U256,
+    start_time: U256,
+    token_id: Address,
+    is_safe_mode: bool,
+    interest_accumulated: U256,
+    penalty_perc: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Account {
+    user: Address,
+    exists: bool,
+    user_id: U256,
+    user_name: String,
+    savings: Vec<Saving>,
+}
+
+struct Options {
+    dry_run: bool,
+    from_block: u64,
+    source_block: Option<u64>,
+    users_file: Option<String>,
+    names_file: Option<String>,
+    batch_size: usize,
+}
+
+impl Options {
+    fn parse() -> eyre::Result<Self> {
+        let mut options = Options {
+            dry_run: false,
+            from_block: 0,
+            source_block: None,
+            users_file: None,
+            names_file: None,
+            batch_size: 20,
+        };
+
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| eyre!("{} needs a value", arg));
+            match arg.as_str() {
+                "--dry-run" => options.dry_run = true,
+                "--from-block" => options.from_block = value()?.parse()?,
+                "--source-block" => options.source_block = Some(value()?.parse()?),
+                "--users" => options.users_file = Some(value()?),
+                "--names" => options.names_file = Some(value()?),
+                "--batch-size" => options.batch_size = value()?.parse()?,
+                _ => return Err(eyre!("Unknown argument {}", arg)),
+            }
+        }
+
+        if options.batch_size == 0 {
+            return Err(eyre!("--batch-size must be positive"));
+        }
+        Ok(options)
+    }
+}
+
+/// Reads raw storage words of a deployment at a fixed block
+struct StorageReader {
+    provider: Arc<Provider<Http>>,
+    address: Address,
+    block: Option<BlockId>,
+}
+
+impl StorageReader {
+    async fn word(&self, slot: U256) -> eyre::Result<[u8; 32]> {
+        let mut key = [0u8; 32];
+        slot.to_big_endian(&mut key);
+        let word = self
+            .provider
+            .get_storage_at(self.address, H256(key), self.block)
+            .await?;
+        Ok(word.0)
+    }
+
+    async fn uint(&self, slot: U256) -> eyre::Result<U256> {
+        Ok(U256::from_big_endian(&self.word(slot).await?))
+    }
+
+    /// Solidity string encoding: short strings inline, long ones at keccak(slot)
+    async fn string(&self, slot: U256) -> eyre::Result<String> {
+        let word = self.word(slot).await?;
+        if word[31] & 1 == 0 {
+            let len = (word[31] / 2) as usize;
+            return Ok(String::from_utf8_lossy(&word[..len]).into_owned());
+        }
+
+        let len = ((U256::from_big_endian(&word) - 1) / 2).as_usize();
+        let data_slot = U256::from(keccak256(slot_bytes(slot)));
+        let mut bytes = Vec::with_capacity(len + 32);
+        for i in 0..len.div_ceil(32) {
+            bytes.extend_from_slice(&self.word(data_slot + i).await?);
+        }
+        bytes.truncate(len);
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    async fn account(&self, user: Address, extra_names: &[String]) -> eyre::Result<Account> {
+        let mut user_key = [0u8; 32];
+        user_key[12..].copy_from_slice(user.as_bytes());
+        let base = mapping_slot(&user_key, U256::from(USERS_MAPPING_SLOT));
+
+        // UserData: user_exists and user_address share the first slot
+        let head = self.word(base).await?;
+        let user_id = self.uint(base + 1).await?;
+        let user_name = self.string(base + 2).await?;
+
+        let mut names = Vec::new();
+        let names_slot = base + 5;
+        let names_data = U256::from(keccak256(slot_bytes(names_slot)));
+        let names_len = self.uint(names_slot).await?.as_usize();
+        for i in 0..names_len {
+            names.push(self.string(names_data + i).await?);
+        }
+        for name in extra_names {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+
+        let mut savings = Vec::new();
+        for name in names {
+            let slot = mapping_slot(name.as_bytes(), base + 4);
+            let start_time = self.uint(slot + 3).await?;
+            if start_time.is_zero() {
+                continue;
+            }
+
+            // token_id and is_safe_mode share a slot
+            let token_word = self.word(slot + 4).await?;
+            savings.push(Saving {
+                name,
+                is_valid: self.word(slot).await?[31] != 0,
+                amount: self.uint(slot + 1).await?,
+                maturity_time: self.uint(slot + 2).await?,
+                start_time,
+                token_id: Address::from_slice(&token_word[12..]),
+                is_safe_mode: token_word[11] != 0,
+                interest_accumulated: self.uint(slot + 5).await?,
+                penalty_perc: self.word(slot + 6).await?[31],
+            });
+        }
+
+        Ok(Account {
+            user,
+            exists: head[31] != 0,
+            user_id,
+            user_name,
+            savings,
+        })
+    }
+}
+
+fn slot_bytes(slot: U256) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    slot.to_big_endian(&mut bytes);
+    bytes
+}
+
+/// Solidity mapping slot: keccak(key . slot)
+fn mapping_slot(key: &[u8], slot: U256) -> U256 {
+    let mut preimage = key.to_vec();
+    preimage.extend_from_slice(&slot_bytes(slot));
+    U256::from(keccak256(preimage))
+}
+
+fn read_lines(path: &str) -> eyre::Result<Vec<String>> {
+    let file = std::fs::File::open(path)?;
+    let mut lines = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            lines.push(line.to_string());
+        }
+    }
+    Ok(lines)
+}
+
+async fn list_users(
+    provider: &Provider<Http>,
+    source: Address,
+    options: &Options,
+) -> eyre::Result<Vec<Address>> {
+    let mut users = Vec::new();
+    if let Some(path) = &options.users_file {
+        for line in read_lines(path)? {
+            users.push(line.parse()?);
+        }
+    }
+
+    let mut filter = Filter::new()
+        .address(source)
+        .events(["UserJoined(address,uint256)", "UserImported(address,uint256)"])
+        .from_block(options.from_block);
+    if let Some(block) = options.source_block {
+        filter = filter.to_block(block);
+    }
+    for log in provider.get_logs(&filter).await? {
+        let user = Address::from(log.topics[1]);
+        if !users.contains(&user) {
+            users.push(user);
+        }
+    }
+
+    Ok(users)
+}
+
+fn diff(source: &Account, target: &Account) -> Vec<String> {
+    let mut changes = Vec::new();
+    if !target.exists {
+        changes.push(format!(
+            "new user #{} `{}`",
+            source.user_id, source.user_name
+        ));
+    } else if source.user_id != target.user_id || source.user_name != target.user_name {
+        changes.push(format!(
+            "user #{} `{}` -> #{} `{}`",
+            target.user_id, target.user_name, source.user_id, source.user_name
+        ));
+    }
+
+    for saving in &source.savings {
+        match target.savings.iter().find(|s| s.name == saving.name) {
+            None => changes.push(format!("new saving {:?}", saving)),
+            Some(existing) if existing != saving => {
+                changes.push(format!("saving {:?} -> {:?}", existing, saving))
+            }
+            Some(_) => {}
+        }
+    }
+
+    for saving in &target.savings {
+        if !source.savings.iter().any(|s| s.name == saving.name) {
+            changes.push(format!("drop saving `{}`", saving.name));
+        }
+    }
+
+    changes
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    dotenv().ok();
+    let options = Options::parse()?;
+
+    let rpc_url = std::env::var(RPC_URL).map_err(|_| eyre!("No {} env var set", RPC_URL))?;
+    let source: Address = std::env::var(STYLUS_PROGRAM_ADDRESS)
+        .map_err(|_| eyre!("No {} env var set", STYLUS_PROGRAM_ADDRESS))?
+        .parse()?;
+    let target: Address = std::env::var(MIGRATION_TARGET_ADDRESS)
+        .map_err(|_| eyre!("No {} env var set", MIGRATION_TARGET_ADDRESS))?
+        .parse()?;
+
+    let provider = Arc::new(Provider::<Http>::try_from(rpc_url)?);
+
+    // pin the source so a live deployment reads consistently
+    let source_block = match options.source_block {
+        Some(block) => block,
+        None => provider.get_block_number().await?.as_u64(),
+    };
+    let source_reader = StorageReader {
+        provider: provider.clone(),
+        address: source,
+        block: Some(BlockId::Number(BlockNumber::Number(source_block.into()))),
+    };
+    let target_reader = StorageReader {
+        provider: provider.clone(),
+        address: target,
+        block: None,
+    };
+
+    let mut extra_names: BTreeMap<Address, Vec<String>> = BTreeMap::new();
+    if let Some(path) = &options.names_file {
+        for line in read_lines(path)? {
+            let (user, name) = line
+                .split_once(',')
+                .ok_or_else(|| eyre!("Expected `address,name`, got `{}`", line))?;
+            extra_names
+                .entry(user.trim().parse()?)
+                .or_default()
+                .push(name.trim().to_string());
+        }
+    }
+
+    let users = list_users(&provider, source, &options).await?;
+    println!("{} users found at block {}", users.len(), source_block);
+
+    let mut accounts = Vec::new();
+    let mut changed = 0;
+    for user in users {
+        let names = extra_names.get(&user).cloned().unwrap_or_default();
+        let account = source_reader.account(user, &names).await?;
+        if !account.exists {
+            println!(
+                "{:?}: not a member at block {}, skipped",
+                user, source_block
+            );
+            continue;
+        }
+
+        if options.dry_run {
+            let names: Vec<String> = account.savings.iter().map(|s| s.name.clone()).collect();
+            let current = target_reader.account(user, &names).await?;
+            let changes = diff(&account, &current);
+            if !changes.is_empty() {
+                changed += 1;
+                println!("{:?}:", user);
+                for change in changes {
+                    println!("  {}", change);
+                }
+            }
+        }
+        accounts.push(account);
+    }
+
+    if options.dry_run {
+        println!(
+            "dry run: {} of {} accounts would change",
+            changed,
+            accounts.len()
+        );
+        return Ok(());
+    }
+
+    let priv_key_path =
+        std::env::var(PRIV_KEY_PATH).map_err(|_| eyre!("No {} env var set", PRIV_KEY_PATH))?;
+    let privkey = read_secret_from_file(&priv_key_path)?;
+    let wallet = LocalWallet::from_str(&privkey)?;
+    let chain_id = provider.get_chainid().await?.as_u64();
+    let client = Arc::new(SignerMiddleware::new(
+        provider.as_ref().clone(),
+        wallet.with_chain_id(chain_id),
+    ));
+    let bitsave = BitsaveMigration::new(target, client);
+
+    if !bitsave.is_migration_open().call().await? {
+        return Err(eyre!("Migration window on {:?} is not open", target));
+    }
+
+    for batch in accounts.chunks(options.batch_size) {
+        let receipt = bitsave
+            .import_users(
+                batch.iter().map(|a| a.user).collect(),
+                batch.iter().map(|a| a.user_id).collect(),
+                batch.iter().map(|a| a.user_name.clone()).collect(),
+            )
+            .send()
+            .await?
+            .await?;
+        println!(
+            "importUsers x{}: {:?}",
+            batch.len(),
+            receipt.map(|r| r.transaction_hash)
+        );
+    }
+
+    for account in &accounts {
+        for batch in account.savings.chunks(options.batch_size) {
+            let receipt = bitsave
+                .import_savings(
+                    account.user,
+                    batch.iter().map(|s| s.name.clone()).collect(),
+                    batch.iter().map(|s| s.token_id).collect(),
+                    batch.iter().map(|s| s.amount).collect(),
+                    batch.iter().map(|s| s.maturity_time).collect(),
+                    batch.iter().map(|s| s.start_time).collect(),
+                    batch.iter().map(|s| s.interest_accumulated).collect(),
+                    batch.iter().map(|s| s.penalty_perc).collect(),
+                    batch.iter().map(|s| s.is_safe_mode).collect(),
+                )
+                .send()
+                .await?
+                .await?;
+            println!(
+                "importSavings {:?} x{}: {:?}",
+                account.user,
+                batch.len(),
+                receipt.map(|r| r.transaction_hash)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn read_secret_from_file(fpath: &str) -> eyre::Result<String> {
+    let f = std::fs::File::open(fpath)?;
+    let mut buf_reader = BufReader::new(f);
+    let mut secret = String::new();
+    buf_reader.read_line(&mut secret)?;
+    Ok(secret.trim().to_string())
+}
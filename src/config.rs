@@ -0,0 +1,120 @@
+use alloy_primitives::{Address, U256, U8};
+use stylus_sdk::{evm, stylus_proc::sol_storage};
+
+use crate::constants::{
+    self,
+    config::{
+        INTEREST_DIVISOR, MAX_INTEREST_DIVISOR, MAX_JOIN_FEE, MAX_SUPPLY, MAX_YEAR_IN_SECONDS,
+        MIN_JOIN_FEE, MIN_YEAR_IN_SECONDS, TOTAL_SUPPLY, YEAR_IN_SECONDS,
+    },
+};
+use crate::errors::{BitsaveErrors, Unauthorized};
+use crate::events::{ConfigAdminTransferred, ConfigUpdated, PauseUpdated};
+
+sol_storage! {
+    /// Economic parameters and the deposit pause, changed through the governance timelock
+    pub struct ProtocolConfig {
+        address admin;
+        mapping(uint8 => uint256) values;
+        mapping(uint8 => bool) is_set;
+        bool paused;
+    }
+}
+
+/// Interest formula inputs, read once from config
+pub struct InterestParams {
+    pub year_in_seconds: U256,
+    pub divisor: U256,
+    pub max_supply: U256,
+    pub total_supply: U256,
+}
+
+impl ProtocolConfig {
+    fn default_value(param: u8) -> U256 {
+        match param {
+            MIN_JOIN_FEE => U256::from(constants::MIN_BS_JOIN_FEE),
+            YEAR_IN_SECONDS => U256::from(constants::YEAR_IN_SECONDS),
+            INTEREST_DIVISOR => U256::from(constants::interest::DIVISOR),
+            MAX_SUPPLY => U256::from(constants::interest::MAX_SUPPLY),
+            _ => U256::from(constants::interest::TOTAL_SUPPLY),
+        }
+    }
+
+    /// Current value of a parameter, falling back to the compile-time default
+    pub fn value(&self, param: u8) -> U256 {
+        if self.is_set.get(U8::from(param)) {
+            return self.values.get(U8::from(param));
+        }
+        Self::default_value(param)
+    }
+
+    pub fn min_join_fee(&self) -> U256 {
+        self.value(MIN_JOIN_FEE)
+    }
+
+    pub fn interest_params(&self) -> InterestParams {
+        InterestParams {
+            year_in_seconds: self.value(YEAR_IN_SECONDS),
+            divisor: self.value(INTEREST_DIVISOR),
+            max_supply: self.value(MAX_SUPPLY),
+            total_supply: self.value(TOTAL_SUPPLY),
+        }
+    }
+
+    pub fn only_admin(&self, caller: Address) -> Result<(), Vec<u8>> {
+        let admin = self.admin.get();
+        if admin == Address::ZERO || admin != caller {
+            return Err(BitsaveErrors::Unauthorized(Unauthorized {}).into());
+        }
+        Ok(())
+    }
+
+    pub fn set_admin(&mut self, new_admin: Address) {
+        let previous_admin = self.admin.get();
+        self.admin.set(new_admin);
+        evm::log(ConfigAdminTransferred {
+            previous_admin,
+            new_admin,
+        });
+    }
+
+    pub fn check_bounds(&self, param: u8, value: U256) -> Result<(), Vec<u8>> {
+        let in_bounds = match param {
+            MIN_JOIN_FEE => value <= U256::from(MAX_JOIN_FEE),
+            YEAR_IN_SECONDS => {
+                value >= U256::from(MIN_YEAR_IN_SECONDS) && value <= U256::from(MAX_YEAR_IN_SECONDS)
+            }
+            INTEREST_DIVISOR => value > U256::ZERO && value <= U256::from(MAX_INTEREST_DIVISOR),
+            MAX_SUPPLY => value > U256::ZERO && value >= self.value(TOTAL_SUPPLY),
+            TOTAL_SUPPLY => value > U256::ZERO && value <= self.value(MAX_SUPPLY),
+            _ => return Err("Unknown config parameter".into()),
+        };
+
+        if !in_bounds {
+            return Err("Config value out of bounds".into());
+        }
+        Ok(())
+    }
+
+    /// Applies a parameter change; reached only through the governance timelock
+    pub fn set_value(&mut self, param: u8, new_value: U256) -> Result<(), Vec<u8>> {
+        // bounds may depend on other parameters changed since queueing
+        self.check_bounds(param, new_value)?;
+
+        let old_value = self.value(param);
+        self.values.setter(U8::from(param)).set(new_value);
+        self.is_set.setter(U8::from(param)).set(true);
+
+        evm::log(ConfigUpdated {
+            param,
+            old_value,
+            new_value,
+        });
+        Ok(())
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused.set(paused);
+        evm::log(PauseUpdated { paused });
+    }
+}
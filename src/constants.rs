@@ -5,4 +5,85 @@ pub mod interest {
     pub const DIVISOR: u64 = 1_000_000; // Define an appropriate divisor as per your logic
     pub const MAX_SUPPLY: u64 = 100_000_000;
     pub const TOTAL_SUPPLY: u64 = 15_000_000;
-}
\ No newline at end of file
+}
+
+pub mod plan {
+    /// Time after a deposit falls due in which a failed pull can be retried;
+    /// only once it has passed does the deposit count as missed
+    pub const GRACE_PERIOD: u64 = 24 * 60 * 60;
+}
+
+pub mod group {
+    /// Payout rules for group savings
+    pub const EQUAL_SPLIT: u8 = 0;
+    pub const PRO_RATA: u8 = 1;
+    pub const ROTATING: u8 = 2;
+}
+
+pub mod rosca {
+    /// Upper bound on circle size, keeps round settlement within gas limits
+    pub const MAX_MEMBERS: usize = 20;
+}
+
+pub mod inheritance {
+    /// Window in which the owner can cancel a beneficiary's claim by any interaction
+    pub const CHALLENGE_PERIOD: u64 = 7 * 24 * 60 * 60;
+}
+
+pub mod recovery {
+    /// Delay between guardians reaching the threshold and the account moving
+    pub const TIMELOCK: u64 = 2 * 24 * 60 * 60;
+}
+
+pub mod config {
+    /// Parameter ids for `ProtocolConfig`
+    pub const MIN_JOIN_FEE: u8 = 0;
+    pub const YEAR_IN_SECONDS: u8 = 1;
+    pub const INTEREST_DIVISOR: u8 = 2;
+    pub const MAX_SUPPLY: u8 = 3;
+    pub const TOTAL_SUPPLY: u8 = 4;
+
+    /// Bounds for parameter changes
+    pub const MAX_JOIN_FEE: u128 = 1_000_000_000_000_000_000; // 1 ether
+    pub const MIN_YEAR_IN_SECONDS: u64 = 360 * 24 * 60 * 60;
+    pub const MAX_YEAR_IN_SECONDS: u64 = 366 * 24 * 60 * 60;
+    pub const MAX_INTEREST_DIVISOR: u128 = 1_000_000_000_000_000_000;
+}
+
+pub mod governance {
+    /// Operation kinds for the timelock queue
+    pub const SET_CONFIG: u8 = 0;
+    pub const TRANSFER_ADMIN: u8 = 1;
+    pub const SET_PAUSED: u8 = 2;
+    pub const UPGRADE: u8 = 3;
+    pub const OPEN_MIGRATION: u8 = 4;
+    pub const SET_STRATEGY: u8 = 5;
+    pub const SPEND_POOL: u8 = 6;
+    pub const SET_VAULT_STATE: u8 = 7;
+
+    /// Delay between queueing an operation and executing it
+    pub const MIN_DELAY: u64 = 2 * 24 * 60 * 60;
+    /// Window after the eta in which a queued operation stays executable
+    pub const GRACE_PERIOD: u64 = 14 * 24 * 60 * 60;
+}
+
+/// How far a saving is counted in its token ledger (`SavingData::ledger_state`)
+pub mod ledger {
+    /// Opened before the ledgers existed; `seed_ledgers` adds it
+    pub const UNCOUNTED: u8 = 0;
+    /// Principal only, opened before `active_savings` was kept
+    pub const PRINCIPAL_ONLY: u8 = 1;
+    /// Principal and `active_savings`
+    pub const COUNTED: u8 = 2;
+}
+
+pub mod strategy {
+    /// Denominator of a strategy's share of principal
+    pub const BPS: u64 = 10_000;
+}
+
+pub mod upgrade {
+    /// Layout version of the `sol_storage!` structs; bump it whenever a
+    /// release changes storage and add the step to `migrate_storage`
+    pub const STORAGE_VERSION: u64 = 8;
+}
@@ -5,6 +5,8 @@ sol! {
     error UserNotExist();
     error InvalidPrice();
     error GeneralError();
+    error NoPendingWithdrawal();
+    error Unauthorized();
 }
 
 pub enum BitsaveErrors {
@@ -12,6 +14,8 @@ pub enum BitsaveErrors {
     GeneralError(GeneralError),
     FromUtf8Error(FromUtf8Error),
     InvalidPrice(InvalidPrice),
+    NoPendingWithdrawal(NoPendingWithdrawal),
+    Unauthorized(Unauthorized),
 }
 
 impl From<BitsaveErrors> for Vec<u8> {
@@ -20,6 +24,8 @@ impl From<BitsaveErrors> for Vec<u8> {
             BitsaveErrors::InvalidPrice(err) => err.encode(),
             BitsaveErrors::UserNotExist(err) => err.encode(),
             BitsaveErrors::GeneralError(err) => err.encode(),
+            BitsaveErrors::NoPendingWithdrawal(err) => err.encode(),
+            BitsaveErrors::Unauthorized(err) => err.encode(),
             BitsaveErrors::FromUtf8Error(err) => err.into_bytes(),
         }
     }
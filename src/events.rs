@@ -0,0 +1,38 @@
+use alloy_sol_types::sol;
+
+sol! {
+    event Transfer(address indexed from, address indexed to, uint256 indexed token_id);
+    event Approval(address indexed owner, address indexed approved, uint256 indexed token_id);
+    event ApprovalForAll(address indexed owner, address indexed operator, bool approved);
+    event ConfigAdminTransferred(address indexed previous_admin, address indexed new_admin);
+    event ConfigUpdated(uint8 indexed param, uint256 old_value, uint256 new_value);
+    event PauseUpdated(bool paused);
+    event OperationQueued(bytes32 indexed id, uint8 kind, uint8 param, uint256 value, address account, uint256 eta);
+    event OperationExecuted(bytes32 indexed id);
+    event OperationCancelled(bytes32 indexed id);
+    event Upgraded(address indexed implementation);
+    event Initialized(uint64 version);
+    event UserJoined(address indexed user, uint256 user_id);
+    event UserImported(address indexed user, uint256 user_id);
+    event MigrationOpened();
+    event MigrationClosed();
+    event LedgersSeeded();
+    event SavingCreated(address indexed user, string name_of_saving, address token_id, uint256 amount, uint256 maturity_time, uint8 penalty_perc, bool safe_mode);
+    event SavingIncremented(address indexed user, string name_of_saving, address token_id, uint256 amount, uint256 interest);
+    event SavingWithdrawn(address indexed user, string name_of_saving, address token_id, uint256 amount, uint256 penalty, uint256 interest_accumulated);
+    event SavingGoalReached(address indexed user, string name_of_saving, uint256 target_amount);
+    event InheritanceClaimStarted(address indexed owner, address indexed claimant);
+    event InheritanceClaimCancelled(address indexed owner);
+    event GuardiansUpdated(address indexed owner, address[] guardians, uint8 threshold);
+    event RecoveryProposed(address indexed owner, address indexed new_address, address guardian);
+    event RecoveryApproved(address indexed owner, address indexed guardian, uint256 approvals);
+    event RecoveryScheduled(address indexed owner, address indexed new_address, uint256 eta);
+    event RecoveryCancelled(address indexed owner);
+    event RecoveryExecuted(address indexed owner, address indexed new_address);
+    event InheritanceClaimed(address indexed owner, address indexed beneficiary, string name_of_saving, uint256 amount);
+    event StrategyUpdated(address indexed token_id, address vault, uint256 share_bps);
+    event StrategyRebalanced(address indexed token_id, address vault, uint256 deployed);
+    event YieldHarvested(address indexed token_id, address vault, uint256 amount);
+    event PoolSpent(address indexed token_id, address to, uint256 amount);
+    event VaultStateUpdated(uint256 old_value, uint256 new_value);
+}
@@ -0,0 +1,88 @@
+use alloy_primitives::{Address, B256, U256};
+use stylus_sdk::{block, crypto, evm, stylus_proc::sol_storage};
+
+use crate::constants::governance::{GRACE_PERIOD, MIN_DELAY};
+use crate::events::{OperationCancelled, OperationExecuted, OperationQueued};
+use crate::signatures::{word_address, word_uint};
+
+sol_storage! {
+    /// Queued privileged operations, keyed by operation id
+    pub struct Timelock {
+        mapping(bytes32 => uint256) etas;
+    }
+}
+
+/// Id of an operation: keccak over its abi-encoded fields. `salt` lets the
+/// same change be queued more than once.
+pub fn operation_id(kind: u8, param: u8, value: U256, account: Address, salt: B256) -> B256 {
+    let mut encoded = Vec::with_capacity(5 * 32);
+    encoded.extend_from_slice(word_uint(U256::from(kind)).as_slice());
+    encoded.extend_from_slice(word_uint(U256::from(param)).as_slice());
+    encoded.extend_from_slice(word_uint(value).as_slice());
+    encoded.extend_from_slice(word_address(account).as_slice());
+    encoded.extend_from_slice(salt.as_slice());
+    crypto::keccak(encoded)
+}
+
+impl Timelock {
+    pub fn eta(&self, id: B256) -> U256 {
+        self.etas.get(id)
+    }
+
+    pub fn queue(
+        &mut self,
+        id: B256,
+        kind: u8,
+        param: u8,
+        value: U256,
+        account: Address,
+    ) -> Result<U256, Vec<u8>> {
+        if self.etas.get(id) != U256::ZERO {
+            return Err("Operation already queued".into());
+        }
+
+        let eta = U256::from(block::timestamp()) + U256::from(MIN_DELAY);
+        self.etas.setter(id).set(eta);
+
+        evm::log(OperationQueued {
+            id: id.0,
+            kind,
+            param,
+            value,
+            account,
+            eta,
+        });
+        Ok(eta)
+    }
+
+    /// Removes a ready operation from the queue so the caller can apply it
+    pub fn take_ready(&mut self, id: B256) -> Result<(), Vec<u8>> {
+        let eta = self.etas.get(id);
+        if eta == U256::ZERO {
+            return Err("Operation not queued".into());
+        }
+
+        let now = U256::from(block::timestamp());
+        if now < eta {
+            return Err("Operation delay still running".into());
+        }
+
+        if now > eta + U256::from(GRACE_PERIOD) {
+            return Err("Operation expired".into());
+        }
+
+        self.etas.setter(id).set(U256::ZERO);
+        evm::log(OperationExecuted { id: id.0 });
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, id: B256) -> Result<(), Vec<u8>> {
+        if self.etas.get(id) == U256::ZERO {
+            return Err("Operation not queued".into());
+        }
+
+        self.etas.setter(id).set(U256::ZERO);
+        evm::log(OperationCancelled { id: id.0 });
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+use alloy_primitives::{Address, U256, U8};
+use stylus_sdk::{block, stylus_proc::sol_storage};
+
+use crate::constants::group::{EQUAL_SPLIT, PRO_RATA, ROTATING};
+
+sol_storage! {
+    pub struct GroupSaving {
+        bool is_valid;
+        address creator;
+        string group_name;
+        address token_id;
+        uint256 maturity_time;
+        uint8 payout_rule;
+        uint256 total_contributed;
+        uint256 total_withdrawn;
+        uint256 rotation_index;
+        address[] members;
+        mapping(address => bool) invited;
+        mapping(address => bool) is_member;
+        mapping(address => uint256) contributions;
+        mapping(address => uint256) withdrawn;
+        // rotating pots: one turn opens every `payout_interval` from `created_at`
+        uint256 created_at;
+        uint256 payout_interval;
+    }
+}
+
+/// When rotation turn `turn` (0-based) opens. Pots from before `payout_interval`
+/// existed have none and open every turn at maturity.
+pub fn turn_opens(
+    created_at: U256,
+    payout_interval: U256,
+    maturity_time: U256,
+    turn: U256,
+) -> U256 {
+    if payout_interval == U256::ZERO {
+        return maturity_time;
+    }
+    created_at + (turn + U256::from(1)) * payout_interval
+}
+
+/// What the member at `member_index` can take from a rotating pot at `now`:
+/// everything collected since the last turn, once their turn has opened
+pub fn rotating_payout(
+    turn: U256,
+    member_index: U256,
+    member_count: U256,
+    opens: U256,
+    now: U256,
+    undistributed: U256,
+) -> U256 {
+    if opens > now || turn % member_count != member_index {
+        return U256::ZERO;
+    }
+    undistributed
+}
+
+impl GroupSaving {
+    pub fn create_group_data(
+        &mut self,
+        creator: Address,
+        group_name: String,
+        token_id: Address,
+        maturity_time: U256,
+        payout_rule: u8,
+        payout_interval: U256,
+    ) -> Result<(), Vec<u8>> {
+        if payout_rule > ROTATING {
+            return Err("Unknown payout rule".into());
+        }
+
+        let now = U256::from(block::timestamp());
+        if maturity_time <= now {
+            return Err("Maturity must be in the future".into());
+        }
+
+        if payout_rule == ROTATING {
+            // the first turn has to come before maturity
+            if payout_interval == U256::ZERO || now + payout_interval > maturity_time {
+                return Err("Payout interval must be non-zero and end before maturity".into());
+            }
+        } else if payout_interval != U256::ZERO {
+            return Err("Only rotating pots have a payout interval".into());
+        }
+
+        self.is_valid.set(true);
+        self.creator.set(creator);
+        self.group_name.set_str(group_name);
+        self.token_id.set(token_id);
+        self.maturity_time.set(maturity_time);
+        self.payout_rule.set(U8::from(payout_rule));
+        self.created_at.set(now);
+        self.payout_interval.set(payout_interval);
+        self.add_member(creator);
+
+        Ok(())
+    }
+
+    fn add_member(&mut self, member: Address) {
+        self.is_member.setter(member).set(true);
+        self.members.push(member);
+    }
+
+    fn is_matured(&self) -> bool {
+        self.maturity_time.get() <= U256::from(block::timestamp())
+    }
+
+    fn is_rotating(&self) -> bool {
+        self.payout_rule.get() == U8::from(ROTATING)
+    }
+
+    fn turn_opens(&self, turn: U256) -> U256 {
+        turn_opens(
+            self.created_at.get(),
+            self.payout_interval.get(),
+            self.maturity_time.get(),
+            turn,
+        )
+    }
+
+    /// Members join until maturity; rotating pots fix their turn order when
+    /// the first turn opens
+    fn check_open(&self) -> Result<(), Vec<u8>> {
+        if self.is_rotating() {
+            if self.turn_opens(U256::ZERO) <= U256::from(block::timestamp()) {
+                return Err("Rotation has started".into());
+            }
+        } else if self.is_matured() {
+            return Err("Group saving has matured".into());
+        }
+        Ok(())
+    }
+
+    pub fn member_count(&self) -> U256 {
+        U256::from(self.members.len())
+    }
+
+    pub fn invite_member(&mut self, caller: Address, member: Address) -> Result<(), Vec<u8>> {
+        if self.creator.get() != caller {
+            return Err("Only the group creator can invite".into());
+        }
+
+        if self.is_member.get(member) {
+            return Err("Already a group member".into());
+        }
+
+        // the equal split divides by the members at maturity; late joiners would dilute it
+        self.check_open()?;
+
+        self.invited.setter(member).set(true);
+        Ok(())
+    }
+
+    pub fn accept_invite(&mut self, member: Address) -> Result<(), Vec<u8>> {
+        if !self.invited.get(member) {
+            return Err("No invite for this group".into());
+        }
+
+        if self.is_member.get(member) {
+            return Err("Already a group member".into());
+        }
+
+        self.check_open()?;
+
+        self.invited.setter(member).set(false);
+        self.add_member(member);
+        Ok(())
+    }
+
+    pub fn contribute(&mut self, member: Address, amount: U256) -> Result<(), Vec<u8>> {
+        if !self.is_member.get(member) {
+            return Err("Not a group member".into());
+        }
+
+        // rotating pots keep collecting between payouts
+        if self.is_matured() && !self.is_rotating() {
+            return Err("Group saving has matured".into());
+        }
+
+        let contribution = self.contributions.get(member);
+        self.contributions.setter(member).set(contribution + amount);
+        self.total_contributed
+            .set(self.total_contributed.get() + amount);
+        Ok(())
+    }
+
+    /// Amount the member can withdraw right now under the group's payout rule
+    pub fn entitlement(&self, member: Address) -> U256 {
+        if !self.is_member.get(member) {
+            return U256::ZERO;
+        }
+
+        let total = self.total_contributed.get();
+        let withdrawn = self.withdrawn.get(member);
+        let rule = self.payout_rule.get().to::<u8>();
+
+        if rule == ROTATING {
+            let turn = self.rotation_index.get();
+            let member_index = (0..self.members.len())
+                .find(|&i| self.members.get(i).unwrap_or_default() == member)
+                .unwrap_or_default();
+            return rotating_payout(
+                turn,
+                U256::from(member_index),
+                self.member_count(),
+                self.turn_opens(turn),
+                U256::from(block::timestamp()),
+                total - self.total_withdrawn.get(),
+            );
+        }
+
+        if !self.is_matured() {
+            return U256::ZERO;
+        }
+
+        let share = match rule {
+            EQUAL_SPLIT => total / self.member_count(),
+            PRO_RATA => self.contributions.get(member),
+            _ => U256::ZERO,
+        };
+
+        share.saturating_sub(withdrawn)
+    }
+
+    /// Records a member's withdrawal and returns the amount to pay out
+    pub fn withdraw(&mut self, member: Address) -> Result<U256, Vec<u8>> {
+        if !self.is_matured() && !self.is_rotating() {
+            return Err("Group saving has not matured".into());
+        }
+
+        let amount = self.entitlement(member);
+        if amount == U256::ZERO {
+            return Err("Nothing to withdraw".into());
+        }
+
+        let withdrawn = self.withdrawn.get(member);
+        self.withdrawn.setter(member).set(withdrawn + amount);
+        self.total_withdrawn
+            .set(self.total_withdrawn.get() + amount);
+
+        if self.is_rotating() {
+            self.rotation_index
+                .set(self.rotation_index.get() + U256::from(1));
+        }
+
+        Ok(amount)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(value: u64) -> U256 {
+        U256::from(value)
+    }
+
+    #[test]
+    fn three_members_are_paid_one_per_interval() {
+        let (created_at, interval, maturity) = (u(1_000), u(100), u(1_300));
+        let members = u(3);
+        let mut collected = U256::ZERO;
+        let mut paid = U256::ZERO;
+
+        for turn in 0..3u64 {
+            // each member pays 10 in during the interval
+            collected += u(30);
+            let opens = turn_opens(created_at, interval, maturity, u(turn));
+            assert_eq!(opens, created_at + interval * u(turn + 1));
+            assert!(opens <= maturity);
+
+            let payout = |member: u64, now: U256| {
+                rotating_payout(u(turn), u(member), members, opens, now, collected - paid)
+            };
+            for member in 0..3 {
+                // nobody is paid before the turn opens
+                assert_eq!(payout(member, opens - u(1)), U256::ZERO);
+                let expected = if member == turn { u(30) } else { U256::ZERO };
+                assert_eq!(payout(member, opens), expected);
+            }
+            paid += payout(turn, opens);
+        }
+
+        assert_eq!(paid, u(90));
+    }
+
+    #[test]
+    fn pots_without_an_interval_open_every_turn_at_maturity() {
+        for turn in 0..3u64 {
+            assert_eq!(
+                turn_opens(u(1_000), U256::ZERO, u(1_300), u(turn)),
+                u(1_300)
+            );
+        }
+    }
+}
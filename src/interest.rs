@@ -0,0 +1,224 @@
+use alloy_primitives::U256;
+
+use crate::config::InterestParams;
+
+/// Bitsave interest rate for one accrual, from the config and vault state
+pub struct Rate {
+    bs_rate: U256,
+    year_in_seconds: U256,
+    divisor: U256,
+}
+
+impl Rate {
+    /// Uses bitsave formulae; to be integrated through the bitsave's token.
+    /// The rate is zero while the vault state or value locked is unset.
+    pub fn new(params: &InterestParams, vault_state: U256, total_value_locked: U256) -> Self {
+        let mut bs_rate = U256::ZERO;
+        if vault_state != U256::ZERO
+            && vault_state <= params.total_supply
+            && total_value_locked != U256::ZERO
+        {
+            // Calculate the Current Reserve Percentage (CRP)
+            let crp = ((params.total_supply - vault_state) * U256::from(100)) / vault_state;
+            if crp != U256::ZERO {
+                bs_rate = params.max_supply / crp.saturating_mul(total_value_locked);
+            }
+        }
+
+        Rate {
+            bs_rate,
+            year_in_seconds: params.year_in_seconds,
+            divisor: params.divisor,
+        }
+    }
+
+    /// Interest on `balance_seconds`, a balance multiplied by the seconds it
+    /// was held
+    pub fn interest(&self, balance_seconds: U256) -> U256 {
+        let denominator = self
+            .year_in_seconds
+            .saturating_mul(U256::from(100))
+            .saturating_mul(self.divisor);
+        if denominator == U256::ZERO {
+            return U256::ZERO;
+        }
+        balance_seconds.saturating_mul(self.bs_rate) / denominator
+    }
+}
+
+/// A saving's time-weighted interest accumulator, as `UserData` stores it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accrual {
+    pub amount: U256,
+    pub start_time: U256,
+    pub maturity_time: U256,
+    /// Zero for savings from before accrual was tracked, whose interest was
+    /// credited up front; they count from their first accrual
+    pub last_accrual: U256,
+    pub balance_seconds: U256,
+    pub interest: U256,
+}
+
+impl Accrual {
+    /// Credits the balance for the time it was locked since the last
+    /// accrual; returns the interest added
+    pub fn accrue(&mut self, rate: &Rate, now: U256) -> U256 {
+        let seconds = if self.last_accrual == U256::ZERO {
+            U256::ZERO
+        } else {
+            locked_seconds(self.last_accrual, self.start_time, self.maturity_time, now)
+        };
+        let balance_seconds = self.amount * seconds;
+        let new_interest = rate.interest(balance_seconds);
+
+        self.balance_seconds += balance_seconds;
+        self.interest += new_interest;
+        self.last_accrual = now;
+        new_interest
+    }
+}
+
+/// Seconds since the last accrual that count toward interest: after the
+/// saving started and before it matured
+pub fn locked_seconds(
+    last_accrual: U256,
+    start_time: U256,
+    maturity_time: U256,
+    now: U256,
+) -> U256 {
+    let from = last_accrual.max(start_time);
+    let to = now.min(maturity_time);
+    to.saturating_sub(from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+    const YEAR: u64 = 365 * DAY;
+    /// A realistic block time; zero would read as "never accrued"
+    const START: u64 = 1_700_000_000;
+
+    fn u(value: u64) -> U256 {
+        U256::from(value)
+    }
+
+    /// 10% a year: max_supply / (crp * tvl) = 1000 / (100 * 1) with a divisor of 1
+    fn ten_percent() -> Rate {
+        let params = InterestParams {
+            year_in_seconds: u(YEAR),
+            divisor: u(1),
+            max_supply: u(1000),
+            total_supply: u(200),
+        };
+        Rate::new(&params, u(100), u(1))
+    }
+
+    fn saving(amount: u64, start_time: u64, maturity_time: u64) -> Accrual {
+        Accrual {
+            amount: u(amount),
+            start_time: u(start_time),
+            maturity_time: u(maturity_time),
+            last_accrual: u(start_time),
+            balance_seconds: U256::ZERO,
+            interest: U256::ZERO,
+        }
+    }
+
+    /// Accrues, then adds the deposit, as `increment_saving_data` does
+    fn increment(saving: &mut Accrual, rate: &Rate, amount: u64, now: u64) {
+        saving.accrue(rate, u(now));
+        saving.amount += u(amount);
+    }
+
+    #[test]
+    fn locked_seconds_stop_at_maturity() {
+        assert_eq!(locked_seconds(u(0), u(100), u(1000), u(400)), u(300));
+        assert_eq!(locked_seconds(u(400), u(100), u(1000), u(700)), u(300));
+        assert_eq!(locked_seconds(u(700), u(100), u(1000), u(5000)), u(300));
+        assert_eq!(
+            locked_seconds(u(1000), u(100), u(1000), u(5000)),
+            U256::ZERO
+        );
+        // clocks never run backwards into a negative interval
+        assert_eq!(locked_seconds(u(900), u(100), u(1000), u(800)), U256::ZERO);
+    }
+
+    #[test]
+    fn interest_is_pro_rata_within_a_year() {
+        let rate = ten_percent();
+        assert_eq!(rate.interest(u(1000) * u(YEAR)), u(100));
+        // half a year earns half, where whole-year rounding gave nothing
+        assert_eq!(rate.interest(u(1000) * u(YEAR / 2)), u(50));
+    }
+
+    #[test]
+    fn late_deposits_earn_for_the_time_they_are_locked() {
+        let rate = ten_percent();
+
+        let mut early = saving(2000, START, START + YEAR);
+        early.accrue(&rate, u(START + YEAR));
+
+        // half the principal arrives with a quarter of the term left
+        let mut late = saving(1000, START, START + YEAR);
+        increment(&mut late, &rate, 1000, START + 3 * YEAR / 4);
+        late.accrue(&rate, u(START + YEAR));
+
+        assert_eq!(early.interest, u(200));
+        assert_eq!(late.interest, u(125));
+        assert_eq!(
+            late.balance_seconds,
+            u(1000) * u(YEAR) + u(1000) * u(YEAR / 4)
+        );
+    }
+
+    #[test]
+    fn nothing_accrues_after_maturity() {
+        let rate = ten_percent();
+        let mut matured = saving(1000, START, START + YEAR);
+        matured.accrue(&rate, u(START + YEAR));
+        increment(&mut matured, &rate, 1000, START + 2 * YEAR);
+        matured.accrue(&rate, u(START + 3 * YEAR));
+
+        assert_eq!(matured.interest, u(100));
+        assert_eq!(matured.balance_seconds, u(1000) * u(YEAR));
+    }
+
+    #[test]
+    fn savings_without_an_accrual_time_count_from_their_first_accrual() {
+        let rate = ten_percent();
+        // interest for the first deposit was credited up front before the upgrade
+        let mut legacy = saving(1000, START, START + YEAR);
+        legacy.last_accrual = U256::ZERO;
+        legacy.interest = u(100);
+
+        assert_eq!(legacy.accrue(&rate, u(START + YEAR / 2)), U256::ZERO);
+        assert_eq!(legacy.last_accrual, u(START + YEAR / 2));
+        assert_eq!(legacy.accrue(&rate, u(START + YEAR)), u(50));
+        assert_eq!(legacy.interest, u(150));
+    }
+
+    #[test]
+    fn unset_vault_state_gives_no_interest() {
+        let params = InterestParams {
+            year_in_seconds: u(YEAR),
+            divisor: u(1),
+            max_supply: u(1000),
+            total_supply: u(200),
+        };
+        assert_eq!(
+            Rate::new(&params, U256::ZERO, u(1)).interest(u(YEAR)),
+            U256::ZERO
+        );
+        assert_eq!(
+            Rate::new(&params, u(100), U256::ZERO).interest(u(YEAR)),
+            U256::ZERO
+        );
+        // no reserve left
+        assert_eq!(
+            Rate::new(&params, u(200), u(1)).interest(u(YEAR)),
+            U256::ZERO
+        );
+    }
+}
@@ -0,0 +1,32 @@
+// `permit` takes more arguments than clippy's default limit
+#![allow(clippy::too_many_arguments)]
+
+use stylus_sdk::prelude::sol_interface;
+
+sol_interface! {
+    interface IERC20 {
+        function transfer(address to, uint256 value) external returns (bool);
+        function transferFrom(address from, address to, uint256 value) external returns (bool);
+        function balanceOf(address account) external view returns (uint256);
+        function approve(address spender, uint256 value) external returns (bool);
+    }
+
+    interface IERC4626 {
+        function asset() external view returns (address);
+        function deposit(uint256 assets, address receiver) external returns (uint256);
+        function withdraw(uint256 assets, address receiver, address owner) external returns (uint256);
+        function convertToAssets(uint256 shares) external view returns (uint256);
+    }
+
+    interface IERC20Permit {
+        function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external;
+    }
+
+    interface IERC1822Proxiable {
+        function proxiableUUID() external view returns (bytes32);
+    }
+
+    interface IERC721Receiver {
+        function onERC721Received(address operator, address from, uint256 token_id, bytes calldata data) external returns (bytes4);
+    }
+}
@@ -0,0 +1,98 @@
+use alloy_primitives::U256;
+use stylus_sdk::stylus_proc::sol_storage;
+
+use crate::constants::ledger::{COUNTED, PRINCIPAL_ONLY, UNCOUNTED};
+
+sol_storage! {
+    /// What the contract owes in one token, kept in step with every deposit
+    /// and payout so solvency can be checked without iterating users
+    pub struct TokenLedger {
+        /// Principal of open individual savings
+        uint256 principal;
+        /// Held for group savings and ROSCA circles
+        uint256 pooled;
+        /// Native payouts credited to `pending_withdrawals`
+        uint256 pending;
+        /// Open individual savings
+        uint256 active_savings;
+        /// Kept back from early withdrawals
+        uint256 penalties;
+        /// Interest credited to withdrawn savings; not paid out in the token
+        uint256 interest_credited;
+        /// Strategy yield, this token's share of the accumulated pool
+        uint256 accumulated_pool;
+    }
+}
+
+// Releases saturate as a safeguard. Savings carry how far they are counted
+// (`constants::ledger`); older ones are completed by `seed_ledgers`.
+impl TokenLedger {
+    pub fn open_saving(&mut self, amount: U256) {
+        self.add_principal(amount);
+        self.active_savings
+            .set(self.active_savings.get() + U256::from(1));
+    }
+
+    /// Releases what was counted of a saving in `ledger_state`
+    pub fn close_saving(&mut self, amount: U256, ledger_state: u8) {
+        if ledger_state >= PRINCIPAL_ONLY {
+            self.principal
+                .set(self.principal.get().saturating_sub(amount));
+        }
+        if ledger_state == COUNTED {
+            self.active_savings
+                .set(self.active_savings.get().saturating_sub(U256::from(1)));
+        }
+    }
+
+    /// Counts the rest of a saving found in `ledger_state`
+    pub fn seed_saving(&mut self, amount: U256, ledger_state: u8) {
+        if ledger_state == UNCOUNTED {
+            self.add_principal(amount);
+        }
+        self.active_savings
+            .set(self.active_savings.get() + U256::from(1));
+    }
+
+    pub fn add_principal(&mut self, amount: U256) {
+        self.principal.set(self.principal.get() + amount);
+    }
+
+    pub fn record_withdrawal(&mut self, penalty: U256, interest: U256) {
+        self.penalties.set(self.penalties.get() + penalty);
+        self.interest_credited
+            .set(self.interest_credited.get() + interest);
+    }
+
+    pub fn add_pooled(&mut self, amount: U256) {
+        self.pooled.set(self.pooled.get() + amount);
+    }
+
+    pub fn release_pooled(&mut self, amount: U256) {
+        self.pooled.set(self.pooled.get().saturating_sub(amount));
+    }
+
+    pub fn add_pending(&mut self, amount: U256) {
+        self.pending.set(self.pending.get() + amount);
+    }
+
+    pub fn release_pending(&mut self, amount: U256) {
+        self.pending.set(self.pending.get().saturating_sub(amount));
+    }
+
+    pub fn add_yield(&mut self, amount: U256) {
+        self.accumulated_pool
+            .set(self.accumulated_pool.get() + amount);
+    }
+
+    pub fn release_yield(&mut self, amount: U256) {
+        self.accumulated_pool
+            .set(self.accumulated_pool.get().saturating_sub(amount));
+    }
+
+    /// Everything owed to savers, groups and circles in this token, and its
+    /// accumulated pool
+    pub fn liabilities(&self) -> U256 {
+        self.principal.get() + self.pooled.get() + self.pending.get() + self.accumulated_pool.get()
+    }
+}
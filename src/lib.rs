@@ -23,6 +23,8 @@
 
 // Allow `cargo stylus export-abi` to generate a main function.
 #![cfg_attr(not(feature = "export-abi"), no_main)]
+// The router the SDK generates for the ABI export nests one iterator per entrypoint
+#![recursion_limit = "256"]
 extern crate alloc;
 
 /// Use an efficient WASM allocator.
@@ -30,19 +32,58 @@ extern crate alloc;
 static ALLOC: mini_alloc::MiniAlloc = mini_alloc::MiniAlloc::INIT;
 
 use alloy_primitives::Address;
+use config::ProtocolConfig;
+use constants::governance::{
+    OPEN_MIGRATION, SET_CONFIG, SET_PAUSED, SET_STRATEGY, SET_VAULT_STATE, SPEND_POOL,
+    TRANSFER_ADMIN, UPGRADE,
+};
+use constants::ledger::{COUNTED, UNCOUNTED};
+use constants::strategy::BPS;
+use constants::upgrade::STORAGE_VERSION;
 /// Import items from the SDK. The prelude contains common traits and macros.
-use errors::{BResult, BitsaveErrors, GeneralError, InvalidPrice, UserNotExist};
+use errors::{
+    BResult, BitsaveErrors, GeneralError, InvalidPrice, NoPendingWithdrawal, UserNotExist,
+};
+use group_data::GroupSaving;
+use events::{
+    Initialized, LedgersSeeded, MigrationClosed, MigrationOpened, PoolSpent, RecoveryExecuted,
+    StrategyRebalanced, StrategyUpdated, UserImported, UserJoined, VaultStateUpdated,
+    YieldHarvested,
+};
+use governance::Timelock;
+use interest::Rate;
+use interfaces::{IERC1822Proxiable, IERC20Permit, IERC721Receiver, IERC20, IERC4626};
+use ledger::TokenLedger;
+use receipts::SavingReceipts;
+use rosca_data::Rosca;
+use strategy::{Erc4626Strategy, StrategyConfig};
 use stylus_sdk::{
-    alloy_primitives::{U256, U8},
+    abi::Bytes,
+    alloy_primitives::{FixedBytes, B256, U256, U8},
+    block,
     call::{call, Call},
-    msg,
+    contract, crypto, evm, msg,
     prelude::*,
 };
-use user_data::UserData;
+use user_data::{SavingRecord, UserData};
+use vault_view::Position;
 
+mod config;
 mod constants;
 mod errors;
+mod events;
+mod governance;
+mod group_data;
+mod interest;
+mod interfaces;
+mod ledger;
+mod proxy;
+mod receipts;
+mod rosca_data;
+mod signatures;
+mod strategy;
 mod user_data;
+mod vault_view;
 
 // Define some persistent storage using the Solidity ABI.
 // `Counter` will be the entrypoint.
@@ -55,9 +96,27 @@ sol_storage! {
         uint256 accumulated_pool_balance;
         uint256 general_fund;
         mapping(address => UserData) users_mapping;
+        mapping(address => uint256) pending_withdrawals;
+        uint256 group_count;
+        mapping(uint256 => GroupSaving) groups_mapping;
+        uint256 rosca_count;
+        mapping(uint256 => Rosca) roscas_mapping;
+        SavingReceipts receipts;
+        mapping(address => uint256) nonces;
+        ProtocolConfig config;
+        Timelock timelock;
         // *** Storage requiring house modifiers ***
-        uint256 public currentVaultState;
-        uint256 public currentTotalValueLocked;
+        // set through a `SET_VAULT_STATE` operation
+        uint256 currentVaultState;
+        // superseded by the per-token `ledgers`, see `get_protocol_stats`
+        uint256 currentTotalValueLocked;
+        // *** Upgrades: append new fields above the gap, shrinking it by the slots they take ***
+        uint256 storage_version;
+        bool migration_open;
+        mapping(address => TokenLedger) ledgers;
+        mapping(address => StrategyConfig) strategies;
+        bool ledger_seeding;
+        uint256[46] __gap;
     }
 }
 
@@ -65,59 +124,89 @@ sol_storage! {
 
 pub type RResult<T, E = Vec<u8>> = core::result::Result<T, E>;
 
-#[external]
+/// Internal helpers, kept out of the external ABI
 impl Bitsave {
-    /// Helpers
-    // get user
-    // fn get_user(&self) -> User_data {
-    //     self.users_mapping.get(msg::sender());
-    //     todo!()
-    // }
+    /// Takes a deposit: the native value for ETH, `amount` pulled from
+    /// `from`'s allowance for tokens. Returns the amount received.
+    fn collect_deposit(
+        &mut self,
+        token_id: Address,
+        from: Address,
+        amount: U256,
+    ) -> Result<U256, Vec<u8>> {
+        self.when_not_paused()?;
 
-    /// Joining bitsave: Initiates
-    /// 1. user mapping (address -> savingsMap)
-    /// 2. user savings names
-    ///
-    pub fn get_bitsave_user_count(&self) -> U256 {
-        self.user_count.get()
-    }
+        if token_id == Address::ZERO {
+            return Ok(msg::value());
+        }
 
-    pub fn get_user_details(&self) -> RResult<(Vec<u8>, U256, Address)> {
-        let user = self.users_mapping.get(msg::sender());
-        if user.user_exists.get() {
-            Ok((
-                user.user_name.get_string().as_bytes().to_vec(),
-                user.user_id.get(),
-                user.user_address.get(),
-            ))
-        } else {
-            Err(BitsaveErrors::UserNotExist(UserNotExist {}).into())
+        if msg::value() != U256::ZERO {
+            return Err("Native value sent for a token deposit".into());
         }
-    }
 
-    pub fn get_bitsave_balance(&self) -> U256 {
-        self.general_fund.get()
+        let pulled = IERC20::new(token_id).transfer_from(
+            Call::new_in(self),
+            from,
+            contract::address(),
+            amount,
+        )?;
+        if !pulled {
+            return Err("Token transfer failed".into());
+        }
+
+        Ok(amount)
     }
 
-    pub fn get_accumulated_pool(&self) -> U256 {
-        self.accumulated_pool_balance.get()
+    /// Deposits are blocked while governance has paused the protocol;
+    /// withdrawals stay open so savers can always exit
+    fn when_not_paused(&self) -> Result<(), Vec<u8>> {
+        if self.config.paused.get() {
+            return Err("Deposits are paused".into());
+        }
+        Ok(())
     }
 
-    pub fn get_tokens_balance(&self) -> U256 {
-        self.token_pool_balance.get()
+    /// Imports are admin-only and limited to a window opened through governance
+    fn only_during_migration(&self) -> Result<(), Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+        if !self.migration_open.get() {
+            return Err("Migration is not open".into());
+        }
+        Ok(())
     }
 
-    #[payable]
-    pub fn fund(&mut self) -> U256 {
-        let new_balance = self.general_fund.get() + msg::value();
-        self.general_fund.set(new_balance);
-        self.general_fund.get()
+    /// Validates an operation when it is queued
+    fn check_operation(
+        &self,
+        kind: u8,
+        param: u8,
+        value: U256,
+        account: Address,
+    ) -> Result<(), Vec<u8>> {
+        match kind {
+            SET_CONFIG => self.config.check_bounds(param, value),
+            TRANSFER_ADMIN if account == Address::ZERO => Err("Invalid admin".into()),
+            TRANSFER_ADMIN => Ok(()),
+            SET_PAUSED if value > U256::from(1) => Err("Invalid pause flag".into()),
+            SET_PAUSED => Ok(()),
+            OPEN_MIGRATION => Ok(()),
+            UPGRADE if !account.has_code() => Err("Implementation has no code".into()),
+            UPGRADE => Ok(()),
+            SET_STRATEGY if value > U256::from(BPS) => Err("Strategy share above 100%".into()),
+            SET_STRATEGY if !account.has_code() => Err("Vault has no code".into()),
+            SET_STRATEGY => Ok(()),
+            SPEND_POOL if value == U256::ZERO => Err("Nothing to spend".into()),
+            SPEND_POOL => Ok(()),
+            SET_VAULT_STATE => self.check_vault_state(value),
+            _ => Err("Unknown operation".into()),
+        }
     }
 
-    #[payable]
-    pub fn join_bitsave(&mut self, user_name: Vec<u8>) -> RResult<Address> {
+    fn join_for(&mut self, user: Address, user_name: Vec<u8>) -> RResult<Address> {
+        self.when_not_paused()?;
+
         // check user doesn't exist
-        let fetched_user = self.users_mapping.get(msg::sender());
+        let fetched_user = self.users_mapping.get(user);
         if fetched_user.user_exists.get() {
             return Err(
                 format!("Member belongs {}", fetched_user.user_address.get())
@@ -127,7 +216,7 @@ impl Bitsave {
         };
 
         // check for joining fee todo
-        if msg::value() < U256::from(constants::MIN_BS_JOIN_FEE) {
+        if msg::value() < self.config.min_join_fee() {
             return Err(BitsaveErrors::InvalidPrice(InvalidPrice {}).into());
         }
 
@@ -135,38 +224,44 @@ impl Bitsave {
         let new_user_count = self.user_count.get() + U256::from(1);
         self.user_count.set(new_user_count);
 
-        let mut fetched_user = self.users_mapping.setter(msg::sender());
+        let mut fetched_user = self.users_mapping.setter(user);
         // update user data
-        fetched_user.create_user(msg::sender(), new_user_count, user_name);
+        fetched_user.create_user(user, new_user_count, user_name);
+        evm::log(UserJoined {
+            user,
+            user_id: new_user_count,
+        });
 
         // return user exists txn
-        Ok(self.users_mapping.get(msg::sender()).user_address.get())
+        Ok(self.users_mapping.get(user).user_address.get())
     }
 
-    /// Create savings:
-    #[payable]
-    pub fn create_saving(
+    #[allow(clippy::too_many_arguments)]
+    fn create_saving_for(
         &mut self,
+        user: Address,
         name_of_saving: String,
+        token_id: Address,
+        amount_of_saving: U256,
         maturity_time: U256,
         penalty_perc: u8,
         use_safe_mode: bool,
     ) -> RResult<()> {
+        self.when_not_paused()?;
+
         // retrieve some data
         // fetch user's data
-        let fetched_user = self.users_mapping.get(msg::sender());
+        let fetched_user = self.users_mapping.get(user);
         if !fetched_user.user_exists.get() {
             println!("User not found");
             return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
         }
 
-        let amount_of_saving = msg::value();
-        let token_id = Address::ZERO; // todo: fix in token address
-
         // user setter
-        let mut user_updater = self.users_mapping.setter(msg::sender());
+        let mut user_updater = self.users_mapping.setter(user);
+        user_updater.record_activity();
         let res = user_updater.create_saving_data(
-            name_of_saving,
+            name_of_saving.clone(),
             amount_of_saving,
             token_id,
             maturity_time,
@@ -177,47 +272,1930 @@ impl Bitsave {
         if let Err(res_err) = res {
             return Err(res_err.into());
         }
+        self.ledgers.setter(token_id).open_saving(amount_of_saving);
+
+        // mint a transferable receipt if the user opted in
+        if user_updater.use_receipts.get() {
+            let receipt_id = self.receipts.mint(user, name_of_saving.clone());
+            let mut user_updater = self.users_mapping.setter(user);
+            user_updater
+                .savings_map
+                .setter(name_of_saving)
+                .receipt_id
+                .set(receipt_id);
+        }
 
         Ok(())
     }
 
-    /// Increment savings
-    pub fn increment_saving(&mut self, name_of_saving: String) -> Result<(), Vec<u8>> {
-        // retrieve some data
-        // fetch user's data
-        let fetched_user = self.users_mapping.get(msg::sender());
+    fn withdraw_saving_for(
+        &mut self,
+        user: Address,
+        name_of_saving: String,
+    ) -> Result<U256, Vec<u8>> {
+        let fetched_user = self.users_mapping.get(user);
         if !fetched_user.user_exists.get() {
             return Err("User doesn't exist".into());
         }
 
-        let amount_to_add = msg::value();
-        let token_id = Address::ZERO; // todo: fix in token address
+        let saving = fetched_user.savings_map.get(name_of_saving.clone());
+        let (token_id, principal) = (saving.token_id.get(), saving.amount.get());
+        let ledger_state = saving.ledger_state.get().to::<u8>();
+        let receipt_id = self.check_receipt_holder(user, name_of_saving.clone(), user)?;
+        let rate = self.interest_rate(token_id);
 
-        // user setter
-        let mut user_updater = self.users_mapping.setter(msg::sender());
-        user_updater.increment_saving_data(name_of_saving, amount_to_add, token_id)?;
+        // user updater
+        let mut user_updater = self.users_mapping.setter(user);
+        user_updater.record_activity();
+        let (with_amount, interest) = user_updater.withdraw_saving_data(name_of_saving, &rate)?;
+        let mut ledger = self.ledgers.setter(token_id);
+        ledger.close_saving(principal, ledger_state);
+        ledger.record_withdrawal(principal - with_amount, interest);
+
+        if receipt_id != U256::ZERO {
+            self.receipts.burn(receipt_id);
+        }
+
+        // transfer funds, credit pending withdrawals if receiver rejects
+        self.pay_out(token_id, user, with_amount)?;
+
+        Ok(with_amount)
+    }
+
+    /// Verifies an EIP-712 signature by `user` over `struct_hash`, then
+    /// consumes the user's nonce
+    fn use_signature(
+        &mut self,
+        user: Address,
+        struct_hash: B256,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<(), Vec<u8>> {
+        if U256::from(block::timestamp()) > deadline {
+            return Err("Signature expired".into());
+        }
+
+        let digest = signatures::typed_data_digest(signatures::domain_separator(), struct_hash);
+        if signatures::recover_signer(digest, v, r, s)? != user {
+            return Err("Invalid signer".into());
+        }
+
+        let nonce = self.nonces.get(user);
+        self.nonces.setter(user).set(nonce + U256::from(1));
         Ok(())
     }
 
-    /// Withdraw savings
-    pub fn withdraw_savings(&mut self, name_of_saving: String) -> Result<U256, Vec<u8>> {
+    /// Errors unless `holder` may act on the saving: savings with a receipt
+    /// follow the receipt's owner. Returns the receipt id, zero if none.
+    fn check_receipt_holder(
+        &self,
+        saver: Address,
+        name_of_saving: String,
+        holder: Address,
+    ) -> Result<U256, Vec<u8>> {
+        let user = self.users_mapping.get(saver);
+        let receipt_id = user.savings_map.get(name_of_saving).receipt_id.get();
+        if receipt_id != U256::ZERO && self.receipts.owner_of(receipt_id)? != holder {
+            return Err("Saving receipt held by another address".into());
+        }
+        Ok(receipt_id)
+    }
 
-        if (msg::reentrant()) {
-            panic!(Err("Reentrant call not allowed!"));
+    /// Calls `onERC721Received` on contract receivers of a safe transfer
+    fn check_receipt_receiver(
+        &mut self,
+        from: Address,
+        to: Address,
+        token_id: U256,
+        data: Vec<u8>,
+    ) -> Result<(), Vec<u8>> {
+        if !to.has_code() {
+            return Ok(());
         }
 
-        let fetched_user = self.users_mapping.get(msg::sender());
-        if !fetched_user.user_exists.get() {
-            return Err("User doesn't exist".into());
+        let retval = IERC721Receiver::new(to).on_erc_721_received(
+            Call::new_in(self),
+            msg::sender(),
+            from,
+            token_id,
+            data,
+        )?;
+        // bytes4(keccak256("onERC721Received(address,address,uint256,bytes)"))
+        if retval != FixedBytes([0x15, 0x0b, 0x7a, 0x02]) {
+            return Err("Receiver rejected the receipt".into());
         }
+        Ok(())
+    }
 
-        // user updater
-        let mut user_updater = self.users_mapping.setter(msg::sender());
-        let with_amount = user_updater.withdraw_saving_data(name_of_saving)?;
+    /// How far the saving is counted in its token ledger, see `constants::ledger`
+    fn ledger_state(&self, user: Address, name_of_saving: String) -> u8 {
+        let user = self.users_mapping.get(user);
+        let ledger_state = user.savings_map.get(name_of_saving).ledger_state.get();
+        ledger_state.to::<u8>()
+    }
+
+    /// Refreshes the inheritance activity clock of `user`, if they've joined
+    fn record_activity(&mut self, user: Address) {
+        let mut user_updater = self.users_mapping.setter(user);
+        if user_updater.user_exists.get() {
+            user_updater.record_activity();
+        }
+    }
 
-        // transfer funds
-        call(Call::new_in(self).value(with_amount), msg::sender(), &[])?;
+    /// Pays out tokens, or ETH crediting pending withdrawals if the receiver rejects it
+    fn pay_out(&mut self, token_id: Address, to: Address, amount: U256) -> Result<(), Vec<u8>> {
+        if token_id != Address::ZERO {
+            self.ensure_liquidity(token_id, amount)?;
+            IERC20::new(token_id).transfer(Call::new_in(self), to, amount)?;
+            return Ok(());
+        }
 
-        Ok(with_amount)
+        if call(Call::new_in(self).value(amount), to, &[]).is_err() {
+            let pending = self.pending_withdrawals.get(to);
+            self.pending_withdrawals.setter(to).set(pending + amount);
+            self.ledgers.setter(Address::ZERO).add_pending(amount);
+        }
+
+        Ok(())
+    }
+
+    /// Points a token's strategy at `vault`; the token is the vault's asset
+    fn set_strategy(&mut self, vault: Address, share_bps: U256) -> Result<(), Vec<u8>> {
+        let token_id = IERC4626::new(vault).asset(&*self)?;
+        if token_id == Address::ZERO {
+            return Err("Strategies need an ERC-20 asset".into());
+        }
+
+        let mut config = self.strategies.setter(token_id);
+        if config.vault.get() != vault && config.deployed.get() != U256::ZERO {
+            return Err("Withdraw from the current vault first".into());
+        }
+        config.vault.set(vault);
+        config.share_bps.set(share_bps);
+
+        evm::log(StrategyUpdated {
+            token_id,
+            vault,
+            share_bps,
+        });
+        Ok(())
+    }
+
+    /// The accumulated pool in a token: the native pool for ETH, harvested
+    /// strategy yield for ERC-20s
+    fn accumulated_pool(&self, token_id: Address) -> U256 {
+        if token_id == Address::ZERO {
+            self.accumulated_pool_balance.get()
+        } else {
+            self.ledgers.get(token_id).accumulated_pool.get()
+        }
+    }
+
+    /// Pays `amount` of a token's accumulated pool to the admin
+    fn spend_pool(&mut self, token_id: Address, amount: U256) -> Result<(), Vec<u8>> {
+        let pool = self.accumulated_pool(token_id);
+        if amount > pool {
+            return Err("Exceeds accumulated pool".into());
+        }
+        if token_id == Address::ZERO {
+            self.accumulated_pool_balance.set(pool - amount);
+        } else {
+            self.ledgers.setter(token_id).release_yield(amount);
+        }
+
+        let to = self.config.admin.get();
+        self.pay_out(token_id, to, amount)?;
+
+        evm::log(PoolSpent {
+            token_id,
+            to,
+            amount,
+        });
+        Ok(())
+    }
+
+    /// The vault state feeds the interest rate's reserve percentage, so it
+    /// must stay between zero and the total supply
+    fn check_vault_state(&self, vault_state: U256) -> Result<(), Vec<u8>> {
+        if vault_state == U256::ZERO || vault_state >= self.config.interest_params().total_supply {
+            return Err("Vault state out of bounds".into());
+        }
+        Ok(())
+    }
+
+    fn set_vault_state(&mut self, new_value: U256) -> Result<(), Vec<u8>> {
+        // the total supply may have changed since queueing
+        self.check_vault_state(new_value)?;
+
+        let old_value = self.currentVaultState.get();
+        self.currentVaultState.set(new_value);
+        evm::log(VaultStateUpdated {
+            old_value,
+            new_value,
+        });
+        Ok(())
+    }
+
+    /// Interest rate for savings in a token, with its principal as the value locked
+    fn interest_rate(&self, token_id: Address) -> Rate {
+        Rate::new(
+            &self.config.interest_params(),
+            self.currentVaultState.get(),
+            self.ledgers.get(token_id).principal.get(),
+        )
+    }
+
+    /// The owner's savings in a token as vault positions
+    fn vault_positions(&self, token_id: Address, owner: Address) -> Vec<Position> {
+        let user_data = self.users_mapping.get(owner);
+        let mut positions = Vec::new();
+        for i in 0..user_data.savings_names.len() {
+            let Some(name) = user_data
+                .savings_names
+                .getter(i)
+                .map(|name| name.get_string())
+            else {
+                continue;
+            };
+            let saving = user_data.savings_map.get(name.clone());
+            if saving.is_valid.get() && saving.token_id.get() == token_id {
+                positions.push(Position {
+                    principal: saving.amount.get(),
+                    payout: user_data.saving_payout(name),
+                });
+            }
+        }
+        positions
+    }
+
+    /// Pulls principal back from the token's vault when the contract's own
+    /// balance can't cover `amount`
+    fn ensure_liquidity(&mut self, token_id: Address, amount: U256) -> Result<(), Vec<u8>> {
+        let config = self.strategies.get(token_id);
+        let (vault, deployed) = (config.vault.get(), config.deployed.get());
+        if deployed == U256::ZERO {
+            return Ok(());
+        }
+
+        let held = IERC20::new(token_id).balance_of(&*self, contract::address())?;
+        if held >= amount {
+            return Ok(());
+        }
+
+        let mut vault_strategy = Erc4626Strategy::new(self, vault, token_id);
+        let deployed = strategy::cover(&mut vault_strategy, deployed, amount - held)?;
+        self.strategies.setter(token_id).deployed.set(deployed);
+        Ok(())
+    }
+}
+
+#[external]
+impl Bitsave {
+    /// Helpers
+    // get user
+    // fn get_user(&self) -> User_data {
+    //     self.users_mapping.get(msg::sender());
+    //     todo!()
+    // }
+
+    /// Joining bitsave: Initiates
+    /// 1. user mapping (address -> savingsMap)
+    /// 2. user savings names
+    ///
+    pub fn get_bitsave_user_count(&self) -> U256 {
+        self.user_count.get()
+    }
+
+    pub fn get_user_details(&self) -> RResult<(Vec<u8>, U256, Address)> {
+        let user = self.users_mapping.get(msg::sender());
+        if user.user_exists.get() {
+            Ok((
+                user.user_name.get_string().as_bytes().to_vec(),
+                user.user_id.get(),
+                user.user_address.get(),
+            ))
+        } else {
+            Err(BitsaveErrors::UserNotExist(UserNotExist {}).into())
+        }
+    }
+
+    pub fn get_bitsave_balance(&self) -> U256 {
+        self.general_fund.get()
+    }
+
+    pub fn get_accumulated_pool(&self) -> U256 {
+        self.accumulated_pool_balance.get()
+    }
+
+    /// Accumulated pool in a token, spent through a `SPEND_POOL` operation
+    pub fn get_token_accumulated_pool(&self, token_id: Address) -> U256 {
+        self.accumulated_pool(token_id)
+    }
+
+    pub fn get_tokens_balance(&self) -> U256 {
+        self.token_pool_balance.get()
+    }
+
+    pub fn get_pending_withdrawal(&self) -> U256 {
+        self.pending_withdrawals.get(msg::sender())
+    }
+
+    /// Returns (principal, pooled, pending) owed in a token
+    pub fn get_token_ledger(&self, token_id: Address) -> (U256, U256, U256) {
+        let ledger = self.ledgers.get(token_id);
+        (
+            ledger.principal.get(),
+            ledger.pooled.get(),
+            ledger.pending.get(),
+        )
+    }
+
+    /// Returns (principal, active_savings, penalties_collected, interest_credited, total_value_locked)
+    /// for a token; the value locked includes group savings and ROSCA funds
+    pub fn get_protocol_stats(&self, token_id: Address) -> (U256, U256, U256, U256, U256) {
+        let ledger = self.ledgers.get(token_id);
+        (
+            ledger.principal.get(),
+            ledger.active_savings.get(),
+            ledger.penalties.get(),
+            ledger.interest_credited.get(),
+            ledger.principal.get() + ledger.pooled.get(),
+        )
+    }
+
+    /// Returns (vault, share_bps, deployed, position) for a token's strategy;
+    /// the position is what the vault shares are worth now
+    pub fn get_strategy(&self, token_id: Address) -> Result<(Address, U256, U256, U256), Vec<u8>> {
+        let config = self.strategies.get(token_id);
+        let vault = config.vault.get();
+        let position = if vault == Address::ZERO {
+            U256::ZERO
+        } else {
+            strategy::position(self, vault)?
+        };
+        Ok((
+            vault,
+            config.share_bps.get(),
+            config.deployed.get(),
+            position,
+        ))
+    }
+
+    /// Moves the token's vault position toward its share of principal,
+    /// depositing only what the contract holds. Returns the deployed amount.
+    pub fn rebalance_strategy(&mut self, token_id: Address) -> Result<U256, Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+
+        let config = self.strategies.get(token_id);
+        let (vault, deployed) = (config.vault.get(), config.deployed.get());
+        if vault == Address::ZERO {
+            return Err("No strategy for token".into());
+        }
+        let target = strategy::target(
+            self.ledgers.get(token_id).principal.get(),
+            config.share_bps.get(),
+        );
+        let idle = IERC20::new(token_id).balance_of(&*self, contract::address())?;
+
+        let mut vault_strategy = Erc4626Strategy::new(self, vault, token_id);
+        let deployed = strategy::rebalance(&mut vault_strategy, deployed, target, idle)?;
+        self.strategies.setter(token_id).deployed.set(deployed);
+
+        evm::log(StrategyRebalanced {
+            token_id,
+            vault,
+            deployed,
+        });
+        Ok(deployed)
+    }
+
+    /// Withdraws the vault's yield into the token's accumulated pool
+    pub fn harvest_strategy(&mut self, token_id: Address) -> Result<U256, Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+
+        let config = self.strategies.get(token_id);
+        let (vault, deployed) = (config.vault.get(), config.deployed.get());
+        if vault == Address::ZERO {
+            return Err("No strategy for token".into());
+        }
+
+        let mut vault_strategy = Erc4626Strategy::new(self, vault, token_id);
+        let amount = strategy::harvest(&mut vault_strategy, deployed)?;
+        self.ledgers.setter(token_id).add_yield(amount);
+
+        evm::log(YieldHarvested {
+            token_id,
+            vault,
+            amount,
+        });
+        Ok(amount)
+    }
+
+    // ERC-4626 views per asset, with `token_id` as the asset. A share is one
+    // unit of principal: yield goes to the accumulated pool, not to savers.
+
+    /// The vault's underlying asset, which is the token itself
+    pub fn vault_asset(&self, token_id: Address) -> Address {
+        token_id
+    }
+
+    /// Principal of every open saving in the token
+    pub fn vault_total_assets(&self, token_id: Address) -> U256 {
+        self.ledgers.get(token_id).principal.get()
+    }
+
+    pub fn vault_convert_to_shares(&self, _token_id: Address, assets: U256) -> U256 {
+        assets
+    }
+
+    pub fn vault_convert_to_assets(&self, _token_id: Address, shares: U256) -> U256 {
+        shares
+    }
+
+    /// Shares of `owner`: principal of their open savings in the token
+    pub fn vault_balance_of(&self, token_id: Address, owner: Address) -> U256 {
+        self.vault_positions(token_id, owner)
+            .iter()
+            .fold(U256::ZERO, |total, position| total + position.principal)
+    }
+
+    /// What `owner` receives withdrawing every saving in the token now,
+    /// after penalties on those still locked
+    pub fn vault_max_withdraw(&self, token_id: Address, owner: Address) -> U256 {
+        vault_view::max_withdraw(&self.vault_positions(token_id, owner))
+    }
+
+    /// Shares `owner` gives up to receive at least `assets`. Savings withdraw
+    /// whole, cheapest penalty first, so the payout may exceed `assets`;
+    /// above `vault_max_withdraw` it is all of the owner's shares.
+    pub fn vault_preview_withdraw(&self, token_id: Address, owner: Address, assets: U256) -> U256 {
+        vault_view::preview_withdraw(&self.vault_positions(token_id, owner), assets)
+    }
+
+    /// Returns (solvent, balance, liabilities, shortfall) for a token.
+    /// Native liabilities include the general fund and accumulated pool.
+    /// Reverts while an upgrade is still seeding the ledgers.
+    pub fn check_invariants(&self, token_id: Address) -> Result<(bool, U256, U256, U256), Vec<u8>> {
+        if self.ledger_seeding.get() {
+            return Err("Ledgers are still being seeded".into());
+        }
+
+        let mut liabilities = self.ledgers.get(token_id).liabilities();
+        let balance = if token_id == Address::ZERO {
+            liabilities += self.general_fund.get() + self.accumulated_pool_balance.get();
+            contract::balance()
+        } else {
+            let vault = self.strategies.get(token_id).vault.get();
+            let deployed = if vault == Address::ZERO {
+                U256::ZERO
+            } else {
+                strategy::position(self, vault)?
+            };
+            IERC20::new(token_id).balance_of(self, contract::address())? + deployed
+        };
+
+        let shortfall = liabilities.saturating_sub(balance);
+        Ok((shortfall == U256::ZERO, balance, liabilities, shortfall))
+    }
+
+    #[payable]
+    pub fn fund(&mut self) -> U256 {
+        let new_balance = self.general_fund.get() + msg::value();
+        self.general_fund.set(new_balance);
+        self.general_fund.get()
+    }
+
+    #[payable]
+    pub fn join_bitsave(&mut self, user_name: Vec<u8>) -> RResult<Address> {
+        self.join_for(msg::sender(), user_name)
+    }
+
+    /// Create savings:
+    #[payable]
+    pub fn create_saving(
+        &mut self,
+        name_of_saving: String,
+        maturity_time: U256,
+        penalty_perc: u8,
+        use_safe_mode: bool,
+    ) -> RResult<()> {
+        let amount_of_saving = msg::value();
+        let token_id = Address::ZERO; // todo: fix in token address
+
+        self.create_saving_for(
+            msg::sender(),
+            name_of_saving,
+            token_id,
+            amount_of_saving,
+            maturity_time,
+            penalty_perc,
+            use_safe_mode,
+        )
+    }
+
+    /// Create a saving with a goal. With `unlock_on_target` it can be
+    /// withdrawn penalty-free once `target_amount` is reached, even before
+    /// maturity; the target must be above the amount sent.
+    #[payable]
+    pub fn create_saving_with_target(
+        &mut self,
+        name_of_saving: String,
+        maturity_time: U256,
+        penalty_perc: u8,
+        use_safe_mode: bool,
+        target_amount: U256,
+        unlock_on_target: bool,
+    ) -> RResult<()> {
+        let amount_of_saving = msg::value();
+        let token_id = Address::ZERO; // todo: fix in token address
+
+        self.create_saving_for(
+            msg::sender(),
+            name_of_saving.clone(),
+            token_id,
+            amount_of_saving,
+            maturity_time,
+            penalty_perc,
+            use_safe_mode,
+        )?;
+        self.users_mapping.setter(msg::sender()).create_target_data(
+            name_of_saving,
+            target_amount,
+            unlock_on_target,
+        )
+    }
+
+    /// Create an ERC-20 saving in one transaction: the token's EIP-2612
+    /// `permit` grants the allowance before `amount` is pulled
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_saving_with_permit(
+        &mut self,
+        token: Address,
+        amount: U256,
+        name_of_saving: String,
+        maturity_time: U256,
+        penalty_perc: u8,
+        use_safe_mode: bool,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> RResult<()> {
+        if token == Address::ZERO {
+            return Err("Permit savings require an ERC-20 token".into());
+        }
+
+        // a front-run permit still leaves the allowance set, so only the pull must succeed
+        let _ = IERC20Permit::new(token).permit(
+            Call::new_in(self),
+            msg::sender(),
+            contract::address(),
+            amount,
+            deadline,
+            v,
+            r,
+            s,
+        );
+        let amount = self.collect_deposit(token, msg::sender(), amount)?;
+
+        self.create_saving_for(
+            msg::sender(),
+            name_of_saving,
+            token,
+            amount,
+            maturity_time,
+            penalty_perc,
+            use_safe_mode,
+        )
+    }
+
+    /// Increment savings
+    #[payable]
+    pub fn increment_saving(&mut self, name_of_saving: String) -> Result<(), Vec<u8>> {
+        self.when_not_paused()?;
+
+        // retrieve some data
+        // fetch user's data
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err("User doesn't exist".into());
+        }
+
+        let amount_to_add = msg::value();
+        let token_id = Address::ZERO; // todo: fix in token address
+        let rate = self.interest_rate(token_id);
+
+        // user setter
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        user_updater.increment_saving_data(
+            name_of_saving.clone(),
+            amount_to_add,
+            token_id,
+            &rate,
+        )?;
+        if self.ledger_state(msg::sender(), name_of_saving) != UNCOUNTED {
+            self.ledgers.setter(token_id).add_principal(amount_to_add);
+        }
+        Ok(())
+    }
+
+    /// Set or raise the goal of an existing saving; the target must be above
+    /// the amount saved. `unlock_on_target` can only be kept or dropped here,
+    /// it is chosen in `create_saving_with_target`.
+    pub fn set_saving_target(
+        &mut self,
+        name_of_saving: String,
+        target_amount: U256,
+        unlock_on_target: bool,
+    ) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        user_updater.set_saving_target(name_of_saving, target_amount, unlock_on_target)
+    }
+
+    /// Names of every saving `user` has opened
+    pub fn get_savings_names(&self, user: Address) -> Vec<String> {
+        let user_data = self.users_mapping.get(user);
+        (0..user_data.savings_names.len())
+            .filter_map(|i| {
+                user_data
+                    .savings_names
+                    .getter(i)
+                    .map(|name| name.get_string())
+            })
+            .collect()
+    }
+
+    /// Returns (is_valid, amount, maturity_time, start_time, token_id, is_safe_mode, interest_accumulated, penalty_perc)
+    pub fn get_saving(
+        &self,
+        user: Address,
+        name_of_saving: String,
+    ) -> (bool, U256, U256, U256, Address, bool, U256, u8) {
+        let user_data = self.users_mapping.get(user);
+        let saving_data = user_data.savings_map.get(name_of_saving);
+        (
+            saving_data.is_valid.get(),
+            saving_data.amount.get(),
+            saving_data.maturity_time.get(),
+            saving_data.start_time.get(),
+            saving_data.token_id.get(),
+            saving_data.is_safe_mode.get(),
+            saving_data.interest_accumulated.get(),
+            saving_data.penalty_perc.get().to::<u8>(),
+        )
+    }
+
+    /// Returns (target_amount, amount, percent_complete, target_reached)
+    pub fn get_saving_progress(&self, name_of_saving: String) -> (U256, U256, U256, bool) {
+        let user = self.users_mapping.get(msg::sender());
+        let saving_data = user.savings_map.get(name_of_saving.clone());
+        (
+            saving_data.target_amount.get(),
+            saving_data.amount.get(),
+            user.saving_progress(name_of_saving),
+            saving_data.target_reached.get(),
+        )
+    }
+
+    /// Create a token saving fed by a recurring plan:
+    /// `amount` of `token` is pulled every `interval` seconds until maturity
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_saving_plan(
+        &mut self,
+        name_of_saving: String,
+        token: Address,
+        amount: U256,
+        interval: U256,
+        maturity_time: U256,
+        penalty_perc: u8,
+        use_safe_mode: bool,
+        max_missed: u8,
+        miss_penalty_step: u8,
+    ) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        if token == Address::ZERO {
+            return Err("Plans require an ERC-20 token".into());
+        }
+
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        user_updater.create_saving_data(
+            name_of_saving.clone(),
+            U256::from(0),
+            token,
+            maturity_time,
+            penalty_perc,
+            use_safe_mode,
+        )?;
+        user_updater.create_plan_data(
+            name_of_saving,
+            token,
+            amount,
+            interval,
+            max_missed,
+            miss_penalty_step,
+        )?;
+        // the saving starts empty; plan deposits add its principal
+        self.ledgers.setter(token).open_saving(U256::ZERO);
+
+        Ok(())
+    }
+
+    /// Execute a due plan deposit; callable by anyone (e.g. a keeper)
+    pub fn execute_plan(&mut self, user: Address, name_of_saving: String) -> Result<bool, Vec<u8>> {
+        self.when_not_paused()?;
+
+        let fetched_user = self.users_mapping.get(user);
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+        let (token_id, amount) = fetched_user.due_plan_data(name_of_saving.clone())?;
+
+        // pull the deposit; a failed pull counts as missed only after the
+        // grace period, so calls timed to a brief allowance or balance dip
+        // can't raise the saver's penalty
+        let deposited = matches!(
+            IERC20::new(token_id).transfer_from(
+                Call::new_in(self),
+                user,
+                contract::address(),
+                amount
+            ),
+            Ok(true)
+        );
+        if !deposited
+            && self
+                .users_mapping
+                .get(user)
+                .plan_in_grace(name_of_saving.clone())
+        {
+            return Err("Plan deposit failed, retry within the grace period".into());
+        }
+
+        let rate = self.interest_rate(token_id);
+        let mut user_updater = self.users_mapping.setter(user);
+        if deposited {
+            user_updater.increment_saving_data(name_of_saving.clone(), amount, token_id, &rate)?;
+        }
+        user_updater.settle_plan_data(name_of_saving.clone(), deposited);
+        if deposited && self.ledger_state(user, name_of_saving) != UNCOUNTED {
+            self.ledgers.setter(token_id).add_principal(amount);
+        }
+
+        Ok(deposited)
+    }
+
+    pub fn cancel_saving_plan(&mut self, name_of_saving: String) -> Result<(), Vec<u8>> {
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        let mut plan = user_updater.plans_map.setter(name_of_saving.clone());
+        if !plan.is_active.get() {
+            return Err(format!("Saving `{}` has no active plan", name_of_saving).into());
+        }
+        plan.is_active.set(false);
+        Ok(())
+    }
+
+    /// Returns (is_active, token, amount, interval, next_due, missed_count, streak)
+    pub fn get_saving_plan(
+        &self,
+        user: Address,
+        name_of_saving: String,
+    ) -> (bool, Address, U256, U256, U256, U256, U256) {
+        let user_data = self.users_mapping.get(user);
+        let plan = user_data.plans_map.get(name_of_saving);
+        (
+            plan.is_active.get(),
+            plan.token_id.get(),
+            plan.amount.get(),
+            plan.interval.get(),
+            plan.next_due.get(),
+            plan.missed_count.get(),
+            plan.streak.get(),
+        )
+    }
+
+    /// Create a shared savings pot; the creator is the first member.
+    /// `payout_rule`: 0 equal split, 1 pro-rata, 2 rotating. Rotating pots pay
+    /// the next member in turn every `payout_interval`, starting one interval
+    /// after creation; the other rules take an interval of 0.
+    pub fn create_group_saving(
+        &mut self,
+        group_name: String,
+        token: Address,
+        maturity_time: U256,
+        payout_rule: u8,
+        payout_interval: U256,
+    ) -> Result<U256, Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        self.record_activity(msg::sender());
+
+        let group_id = self.group_count.get() + U256::from(1);
+        self.group_count.set(group_id);
+
+        let mut group = self.groups_mapping.setter(group_id);
+        group.create_group_data(
+            msg::sender(),
+            group_name,
+            token,
+            maturity_time,
+            payout_rule,
+            payout_interval,
+        )?;
+
+        Ok(group_id)
+    }
+
+    pub fn invite_to_group(&mut self, group_id: U256, member: Address) -> Result<(), Vec<u8>> {
+        let mut group = self.groups_mapping.setter(group_id);
+        if !group.is_valid.get() {
+            return Err("Group doesn't exist".into());
+        }
+        group.invite_member(msg::sender(), member)?;
+
+        self.record_activity(msg::sender());
+        Ok(())
+    }
+
+    pub fn join_group(&mut self, group_id: U256) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let mut group = self.groups_mapping.setter(group_id);
+        if !group.is_valid.get() {
+            return Err("Group doesn't exist".into());
+        }
+        group.accept_invite(msg::sender())?;
+
+        self.record_activity(msg::sender());
+        Ok(())
+    }
+
+    /// Contribute to a group pot: native value for ETH pots, `amount` of the
+    /// group token (pulled from allowance) otherwise
+    #[payable]
+    pub fn contribute_to_group(&mut self, group_id: U256, amount: U256) -> Result<U256, Vec<u8>> {
+        let group = self.groups_mapping.get(group_id);
+        if !group.is_valid.get() {
+            return Err("Group doesn't exist".into());
+        }
+
+        let token_id = group.token_id.get();
+        let amount = self.collect_deposit(token_id, msg::sender(), amount)?;
+
+        let mut group = self.groups_mapping.setter(group_id);
+        group.contribute(msg::sender(), amount)?;
+        self.ledgers.setter(token_id).add_pooled(amount);
+        self.record_activity(msg::sender());
+
+        Ok(amount)
+    }
+
+    /// Withdraw the caller's share of a matured group pot, or the collected
+    /// pot of a rotating group once the caller's turn has opened
+    pub fn withdraw_from_group(&mut self, group_id: U256) -> Result<U256, Vec<u8>> {
+        if msg::reentrant() {
+            return Err("Reentrant call not allowed!".into());
+        }
+
+        let mut group = self.groups_mapping.setter(group_id);
+        if !group.is_valid.get() {
+            return Err("Group doesn't exist".into());
+        }
+        let token_id = group.token_id.get();
+        let amount = group.withdraw(msg::sender())?;
+        self.ledgers.setter(token_id).release_pooled(amount);
+        self.record_activity(msg::sender());
+
+        self.pay_out(token_id, msg::sender(), amount)?;
+        Ok(amount)
+    }
+
+    /// Returns (creator, token, maturity_time, payout_rule, total_contributed, total_withdrawn, member_count)
+    pub fn get_group(&self, group_id: U256) -> (Address, Address, U256, u8, U256, U256, U256) {
+        let group = self.groups_mapping.get(group_id);
+        (
+            group.creator.get(),
+            group.token_id.get(),
+            group.maturity_time.get(),
+            group.payout_rule.get().to::<u8>(),
+            group.total_contributed.get(),
+            group.total_withdrawn.get(),
+            group.member_count(),
+        )
+    }
+
+    /// Returns (is_member, contributed, withdrawn, withdrawable_now)
+    pub fn get_group_member(&self, group_id: U256, member: Address) -> (bool, U256, U256, U256) {
+        let group = self.groups_mapping.get(group_id);
+        (
+            group.is_member.get(member),
+            group.contributions.get(member),
+            group.withdrawn.get(member),
+            group.entitlement(member),
+        )
+    }
+
+    /// Create a rotating savings circle. Each round every member pays
+    /// `contribution_amount` before the deadline and one member receives the pot,
+    /// in join order or by lottery. Members post `collateral_amount` on joining.
+    #[payable]
+    pub fn create_rosca(
+        &mut self,
+        token: Address,
+        contribution_amount: U256,
+        collateral_amount: U256,
+        round_duration: U256,
+        penalty_perc: u8,
+        use_lottery: bool,
+    ) -> Result<U256, Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        self.record_activity(msg::sender());
+
+        let rosca_id = self.rosca_count.get() + U256::from(1);
+        self.rosca_count.set(rosca_id);
+
+        let mut rosca = self.roscas_mapping.setter(rosca_id);
+        rosca.create_rosca_data(
+            msg::sender(),
+            token,
+            contribution_amount,
+            collateral_amount,
+            round_duration,
+            penalty_perc,
+            use_lottery,
+        )?;
+
+        let collateral = self.collect_deposit(token, msg::sender(), collateral_amount)?;
+        self.roscas_mapping
+            .setter(rosca_id)
+            .add_member(msg::sender(), collateral)?;
+        self.ledgers.setter(token).add_pooled(collateral);
+
+        Ok(rosca_id)
+    }
+
+    #[payable]
+    pub fn join_rosca(&mut self, rosca_id: U256) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let rosca = self.roscas_mapping.get(rosca_id);
+        if !rosca.is_valid.get() {
+            return Err("Circle doesn't exist".into());
+        }
+
+        let token_id = rosca.token_id.get();
+        let collateral_amount = rosca.collateral_amount.get();
+        let collateral = self.collect_deposit(token_id, msg::sender(), collateral_amount)?;
+
+        self.roscas_mapping
+            .setter(rosca_id)
+            .add_member(msg::sender(), collateral)?;
+        self.ledgers.setter(token_id).add_pooled(collateral);
+        self.record_activity(msg::sender());
+        Ok(())
+    }
+
+    pub fn start_rosca(&mut self, rosca_id: U256) -> Result<(), Vec<u8>> {
+        let mut rosca = self.roscas_mapping.setter(rosca_id);
+        if !rosca.is_valid.get() {
+            return Err("Circle doesn't exist".into());
+        }
+        rosca.start(msg::sender())?;
+
+        self.record_activity(msg::sender());
+        Ok(())
+    }
+
+    #[payable]
+    pub fn contribute_rosca(&mut self, rosca_id: U256) -> Result<(), Vec<u8>> {
+        let rosca = self.roscas_mapping.get(rosca_id);
+        if !rosca.is_valid.get() {
+            return Err("Circle doesn't exist".into());
+        }
+
+        let token_id = rosca.token_id.get();
+        let contribution_amount = rosca.contribution_amount.get();
+        let amount = self.collect_deposit(token_id, msg::sender(), contribution_amount)?;
+
+        self.roscas_mapping
+            .setter(rosca_id)
+            .contribute(msg::sender(), amount)?;
+        self.ledgers.setter(token_id).add_pooled(amount);
+        self.record_activity(msg::sender());
+        Ok(())
+    }
+
+    /// Settle the current round once its deadline passes (or everyone paid);
+    /// callable by anyone. Returns the recipient of the round's pot.
+    pub fn close_rosca_round(&mut self, rosca_id: U256) -> Result<Address, Vec<u8>> {
+        if msg::reentrant() {
+            return Err("Reentrant call not allowed!".into());
+        }
+
+        let mut rosca = self.roscas_mapping.setter(rosca_id);
+        if !rosca.is_valid.get() {
+            return Err("Circle doesn't exist".into());
+        }
+        let token_id = rosca.token_id.get();
+        let (recipient, payout) = rosca.close_round(rosca_id)?;
+        self.ledgers.setter(token_id).release_pooled(payout);
+        self.record_activity(msg::sender());
+
+        self.pay_out(token_id, recipient, payout)?;
+        Ok(recipient)
+    }
+
+    /// Withdraw remaining collateral after the circle's final round
+    pub fn withdraw_rosca_collateral(&mut self, rosca_id: U256) -> Result<U256, Vec<u8>> {
+        if msg::reentrant() {
+            return Err("Reentrant call not allowed!".into());
+        }
+
+        let mut rosca = self.roscas_mapping.setter(rosca_id);
+        if !rosca.is_valid.get() {
+            return Err("Circle doesn't exist".into());
+        }
+        let token_id = rosca.token_id.get();
+        let collateral = rosca.release_collateral(msg::sender())?;
+        self.ledgers.setter(token_id).release_pooled(collateral);
+        self.record_activity(msg::sender());
+
+        self.pay_out(token_id, msg::sender(), collateral)?;
+        Ok(collateral)
+    }
+
+    /// Returns (token, contribution_amount, member_count, current_round, round_deadline, round_pot, is_finished)
+    pub fn get_rosca(&self, rosca_id: U256) -> (Address, U256, U256, U256, U256, U256, bool) {
+        let rosca = self.roscas_mapping.get(rosca_id);
+        (
+            rosca.token_id.get(),
+            rosca.contribution_amount.get(),
+            U256::from(rosca.members.len()),
+            rosca.current_round.get(),
+            rosca.round_deadline(),
+            rosca.round_pot.get(),
+            rosca.is_finished.get(),
+        )
+    }
+
+    /// Payout history: recipients and amounts for every settled round
+    pub fn get_rosca_payouts(&self, rosca_id: U256) -> (Vec<Address>, Vec<U256>) {
+        let rosca = self.roscas_mapping.get(rosca_id);
+        let recipients = (0..rosca.payout_recipients.len())
+            .filter_map(|i| rosca.payout_recipients.get(i))
+            .collect();
+        let amounts = (0..rosca.payout_amounts.len())
+            .filter_map(|i| rosca.payout_amounts.get(i))
+            .collect();
+        (recipients, amounts)
+    }
+
+    /// Nominate an account-wide beneficiary who may claim matured savings
+    /// after `inactivity_timeout` seconds without any owner interaction
+    pub fn set_beneficiary(
+        &mut self,
+        beneficiary: Address,
+        inactivity_timeout: U256,
+    ) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        user_updater.set_beneficiary_data(beneficiary, inactivity_timeout);
+        Ok(())
+    }
+
+    /// Nominate a beneficiary for a single saving, overriding the account's
+    pub fn set_saving_beneficiary(
+        &mut self,
+        name_of_saving: String,
+        beneficiary: Address,
+    ) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        user_updater.set_saving_beneficiary_data(name_of_saving, beneficiary)
+    }
+
+    /// Explicit proof of life; cancels any pending inheritance claim
+    pub fn cancel_inheritance_claim(&mut self) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        self.users_mapping.setter(msg::sender()).record_activity();
+        Ok(())
+    }
+
+    /// Start the challenge period on an inactive owner's account
+    pub fn start_inheritance_claim(&mut self, owner: Address) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(owner);
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        self.users_mapping.setter(owner).start_claim(msg::sender())
+    }
+
+    /// Claim a matured saving once the challenge period passed uncontested
+    pub fn claim_inheritance(
+        &mut self,
+        owner: Address,
+        name_of_saving: String,
+    ) -> Result<U256, Vec<u8>> {
+        if msg::reentrant() {
+            return Err("Reentrant call not allowed!".into());
+        }
+
+        let fetched_user = self.users_mapping.get(owner);
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let receipt_id = self.check_receipt_holder(owner, name_of_saving.clone(), owner)?;
+        let ledger_state = self.ledger_state(owner, name_of_saving.clone());
+
+        let mut owner_updater = self.users_mapping.setter(owner);
+        let (token_id, amount) = owner_updater.claim_saving_data(msg::sender(), name_of_saving)?;
+        self.ledgers
+            .setter(token_id)
+            .close_saving(amount, ledger_state);
+
+        if receipt_id != U256::ZERO {
+            self.receipts.burn(receipt_id);
+        }
+
+        self.pay_out(token_id, msg::sender(), amount)?;
+        Ok(amount)
+    }
+
+    /// Returns (beneficiary, inactivity_timeout, last_active, claim_started_at)
+    pub fn get_beneficiary(&self, owner: Address) -> (Address, U256, U256, U256) {
+        let user = self.users_mapping.get(owner);
+        (
+            user.beneficiary.get(),
+            user.inactivity_timeout.get(),
+            user.last_active.get(),
+            user.claim_started_at.get(),
+        )
+    }
+
+    /// Set the guardians who can move this account to a new address,
+    /// `threshold` of them must approve. Resets any pending recovery.
+    pub fn set_guardians(&mut self, guardians: Vec<Address>, threshold: u8) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        user_updater.set_guardians_data(guardians, threshold)
+    }
+
+    /// Guardian proposes moving `owner`'s account to `new_address` (counts as an approval)
+    pub fn propose_recovery(&mut self, owner: Address, new_address: Address) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(owner);
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        self.users_mapping
+            .setter(owner)
+            .propose_recovery_data(msg::sender(), new_address)
+    }
+
+    pub fn approve_recovery(&mut self, owner: Address) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(owner);
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        self.users_mapping
+            .setter(owner)
+            .approve_recovery_data(msg::sender())
+    }
+
+    /// Owner cancels a pending recovery with the original key
+    pub fn cancel_recovery(&mut self) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        user_updater.cancel_recovery_data()
+    }
+
+    /// Move the account, all its savings and pending withdrawals to the
+    /// approved address once the timelock has elapsed; callable by anyone.
+    /// Group and circle memberships stay with the old address.
+    pub fn execute_recovery(&mut self, owner: Address) -> Result<Address, Vec<u8>> {
+        let fetched_user = self.users_mapping.get(owner);
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let new_address = fetched_user.executable_recovery()?;
+        if self.users_mapping.get(new_address).user_exists.get() {
+            return Err(format!("Member belongs {}", new_address).into());
+        }
+
+        let record = self.users_mapping.get(owner).export_account();
+        let receipt_ids: Vec<U256> = record
+            .savings
+            .iter()
+            .map(|saving| saving.receipt_id)
+            .filter(|receipt_id| *receipt_id != U256::ZERO)
+            .collect();
+        self.users_mapping.setter(owner).clear_account();
+        self.users_mapping
+            .setter(new_address)
+            .import_account(new_address, record);
+
+        // receipts still held by the old key move with the account
+        for receipt_id in receipt_ids {
+            self.receipts.savers.setter(receipt_id).set(new_address);
+            if self.receipts.owners.get(receipt_id) == owner {
+                self.receipts.move_receipt(owner, new_address, receipt_id);
+            }
+        }
+
+        let pending = self.pending_withdrawals.get(owner);
+        self.pending_withdrawals.setter(owner).set(U256::ZERO);
+        let new_pending = self.pending_withdrawals.get(new_address);
+        self.pending_withdrawals
+            .setter(new_address)
+            .set(new_pending + pending);
+
+        evm::log(RecoveryExecuted {
+            owner,
+            new_address,
+        });
+        Ok(new_address)
+    }
+
+    /// Returns (guardians, threshold, recovery_target, approvals, eta)
+    pub fn get_recovery(&self, owner: Address) -> (Vec<Address>, u8, Address, U256, U256) {
+        let user = self.users_mapping.get(owner);
+        let guardians = (0..user.guardians.len())
+            .filter_map(|i| user.guardians.get(i))
+            .collect();
+        (
+            guardians,
+            user.recovery_threshold.get().to::<u8>(),
+            user.recovery_target.get(),
+            user.recovery_approval_count.get(),
+            user.recovery_eta.get(),
+        )
+    }
+
+    /// Opt in to ERC-721 receipts for savings created from now on.
+    /// Withdrawal rights follow whoever holds a saving's receipt.
+    pub fn set_saving_receipts(&mut self, enabled: bool) -> Result<(), Vec<u8>> {
+        let fetched_user = self.users_mapping.get(msg::sender());
+        if !fetched_user.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        let mut user_updater = self.users_mapping.setter(msg::sender());
+        user_updater.record_activity();
+        user_updater.use_receipts.set(enabled);
+        Ok(())
+    }
+
+    /// Withdraw the saving behind a receipt to its current holder, burning the receipt
+    pub fn withdraw_receipt(&mut self, receipt_id: U256) -> Result<U256, Vec<u8>> {
+        if msg::reentrant() {
+            return Err("Reentrant call not allowed!".into());
+        }
+
+        if self.receipts.owner_of(receipt_id)? != msg::sender() {
+            return Err("Caller doesn't hold the receipt".into());
+        }
+
+        let saver = self.receipts.savers.get(receipt_id);
+        let name_of_saving = self.receipts.saving_names.get(receipt_id).get_string();
+        let saver_data = self.users_mapping.get(saver);
+        let saving = saver_data.savings_map.get(name_of_saving.clone());
+        let (token_id, principal) = (saving.token_id.get(), saving.amount.get());
+        let ledger_state = saving.ledger_state.get().to::<u8>();
+        let rate = self.interest_rate(token_id);
+
+        let mut user_updater = self.users_mapping.setter(saver);
+        let (with_amount, interest) = user_updater.withdraw_saving_data(name_of_saving, &rate)?;
+        let mut ledger = self.ledgers.setter(token_id);
+        ledger.close_saving(principal, ledger_state);
+        ledger.record_withdrawal(principal - with_amount, interest);
+        self.receipts.burn(receipt_id);
+        self.record_activity(msg::sender());
+
+        self.pay_out(token_id, msg::sender(), with_amount)?;
+        Ok(with_amount)
+    }
+
+    pub fn name(&self) -> String {
+        "Bitsave Saving".into()
+    }
+
+    pub fn symbol(&self) -> String {
+        "BSAVE".into()
+    }
+
+    #[selector(name = "tokenURI")]
+    pub fn token_uri(&self, receipt_id: U256) -> Result<String, Vec<u8>> {
+        self.receipts.owner_of(receipt_id)?;
+
+        let saver = self.receipts.savers.get(receipt_id);
+        let name_of_saving = self.receipts.saving_names.get(receipt_id).get_string();
+        let user = self.users_mapping.get(saver);
+        let saving_data = user.savings_map.get(name_of_saving.clone());
+
+        Ok(receipts::token_uri_json(
+            receipt_id,
+            &name_of_saving,
+            saving_data.amount.get(),
+            saving_data.token_id.get(),
+            saving_data.maturity_time.get(),
+            saving_data.penalty_perc.get().to::<u8>(),
+        ))
+    }
+
+    pub fn balance_of(&self, owner: Address) -> Result<U256, Vec<u8>> {
+        if owner == Address::ZERO {
+            return Err("Balance query for the zero address".into());
+        }
+        Ok(self.receipts.balances.get(owner))
+    }
+
+    pub fn owner_of(&self, receipt_id: U256) -> Result<Address, Vec<u8>> {
+        self.receipts.owner_of(receipt_id)
+    }
+
+    pub fn get_approved(&self, receipt_id: U256) -> Result<Address, Vec<u8>> {
+        self.receipts.owner_of(receipt_id)?;
+        Ok(self.receipts.token_approvals.get(receipt_id))
+    }
+
+    pub fn is_approved_for_all(&self, owner: Address, operator: Address) -> bool {
+        self.receipts.operator_approvals.get(owner).get(operator)
+    }
+
+    pub fn approve(&mut self, approved: Address, receipt_id: U256) -> Result<(), Vec<u8>> {
+        self.receipts.approve(msg::sender(), approved, receipt_id)?;
+        self.record_activity(msg::sender());
+        Ok(())
+    }
+
+    pub fn set_approval_for_all(&mut self, operator: Address, approved: bool) {
+        self.receipts
+            .set_approval_for_all(msg::sender(), operator, approved);
+        self.record_activity(msg::sender());
+    }
+
+    pub fn transfer_from(
+        &mut self,
+        from: Address,
+        to: Address,
+        receipt_id: U256,
+    ) -> Result<(), Vec<u8>> {
+        self.receipts
+            .transfer(msg::sender(), from, to, receipt_id)?;
+        self.record_activity(msg::sender());
+        Ok(())
+    }
+
+    pub fn safe_transfer_from(
+        &mut self,
+        from: Address,
+        to: Address,
+        receipt_id: U256,
+    ) -> Result<(), Vec<u8>> {
+        self.receipts
+            .transfer(msg::sender(), from, to, receipt_id)?;
+        self.record_activity(msg::sender());
+        self.check_receipt_receiver(from, to, receipt_id, Vec::new())
+    }
+
+    #[selector(name = "safeTransferFrom")]
+    pub fn safe_transfer_from_with_data(
+        &mut self,
+        from: Address,
+        to: Address,
+        receipt_id: U256,
+        data: Bytes,
+    ) -> Result<(), Vec<u8>> {
+        self.receipts
+            .transfer(msg::sender(), from, to, receipt_id)?;
+        self.record_activity(msg::sender());
+        self.check_receipt_receiver(from, to, receipt_id, data.0)
+    }
+
+    /// ERC-165: ERC-165, ERC-721 and ERC-721 metadata
+    pub fn supports_interface(&self, interface_id: FixedBytes<4>) -> bool {
+        interface_id == FixedBytes([0x01, 0xff, 0xc9, 0xa7])
+            || interface_id == FixedBytes([0x80, 0xac, 0x58, 0xcd])
+            || interface_id == FixedBytes([0x5b, 0x5e, 0x13, 0x9f])
+    }
+
+    /// Current meta-transaction nonce of `user`
+    pub fn get_nonce(&self, user: Address) -> U256 {
+        self.nonces.get(user)
+    }
+
+    /// EIP-712 domain separator for this deployment
+    pub fn domain_separator(&self) -> B256 {
+        signatures::domain_separator()
+    }
+
+    /// Join on behalf of `user`; the relayer pays the joining fee
+    #[payable]
+    pub fn join_bitsave_by_sig(
+        &mut self,
+        user: Address,
+        user_name: Vec<u8>,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> RResult<Address> {
+        let struct_hash = signatures::hash_struct(
+            signatures::JOIN_TYPE,
+            &[
+                signatures::word_address(user),
+                crypto::keccak(&user_name),
+                signatures::word_uint(self.nonces.get(user)),
+                signatures::word_uint(deadline),
+            ],
+        );
+        self.use_signature(user, struct_hash, deadline, v, r, s)?;
+
+        self.join_for(user, user_name)
+    }
+
+    /// Create a saving on behalf of `user`. Token savings pull `amount` from
+    /// the user's allowance; ETH savings are funded by the relayer's value.
+    #[allow(clippy::too_many_arguments)]
+    #[payable]
+    pub fn create_saving_by_sig(
+        &mut self,
+        user: Address,
+        name_of_saving: String,
+        token: Address,
+        amount: U256,
+        maturity_time: U256,
+        penalty_perc: u8,
+        use_safe_mode: bool,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> RResult<()> {
+        let struct_hash = signatures::hash_struct(
+            signatures::CREATE_SAVING_TYPE,
+            &[
+                signatures::word_address(user),
+                crypto::keccak(&name_of_saving),
+                signatures::word_address(token),
+                signatures::word_uint(amount),
+                signatures::word_uint(maturity_time),
+                signatures::word_uint(U256::from(penalty_perc)),
+                signatures::word_bool(use_safe_mode),
+                signatures::word_uint(self.nonces.get(user)),
+                signatures::word_uint(deadline),
+            ],
+        );
+        self.use_signature(user, struct_hash, deadline, v, r, s)?;
+
+        if token == Address::ZERO && msg::value() != amount {
+            return Err(BitsaveErrors::InvalidPrice(InvalidPrice {}).into());
+        }
+        let amount = self.collect_deposit(token, user, amount)?;
+
+        self.create_saving_for(
+            user,
+            name_of_saving,
+            token,
+            amount,
+            maturity_time,
+            penalty_perc,
+            use_safe_mode,
+        )
+    }
+
+    /// Withdraw a saving on behalf of `user`; funds go to the user
+    pub fn withdraw_savings_by_sig(
+        &mut self,
+        user: Address,
+        name_of_saving: String,
+        deadline: U256,
+        v: u8,
+        r: B256,
+        s: B256,
+    ) -> Result<U256, Vec<u8>> {
+        if msg::reentrant() {
+            return Err("Reentrant call not allowed!".into());
+        }
+
+        let struct_hash = signatures::hash_struct(
+            signatures::WITHDRAW_TYPE,
+            &[
+                signatures::word_address(user),
+                crypto::keccak(&name_of_saving),
+                signatures::word_uint(self.nonces.get(user)),
+                signatures::word_uint(deadline),
+            ],
+        );
+        self.use_signature(user, struct_hash, deadline, v, r, s)?;
+
+        self.withdraw_saving_for(user, name_of_saving)
+    }
+
+    /// Replaces constructor logic: sets the admin and the storage version.
+    /// Only runs through the proxy, as its init call, so the implementation
+    /// itself can't be claimed.
+    pub fn initialize(&mut self, admin: Address) -> Result<(), Vec<u8>> {
+        proxy::check_initialize(self.storage_version.get(), proxy::is_delegated(), admin)?;
+
+        self.config.set_admin(admin);
+        self.storage_version.set(U256::from(STORAGE_VERSION));
+        evm::log(Initialized {
+            version: STORAGE_VERSION,
+        });
+        Ok(())
+    }
+
+    /// Brings storage written by an older implementation up to this
+    /// release's layout; run right after an upgrade
+    pub fn migrate_storage(&mut self) -> Result<U256, Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+
+        let stored = self.storage_version.get();
+        if stored == U256::ZERO {
+            return Err("Not initialized".into());
+        }
+
+        if stored > U256::from(STORAGE_VERSION) {
+            return Err("Storage is newer than this implementation".into());
+        }
+
+        // per-version migration steps, each run once when coming from below it
+        if stored < U256::from(2) {
+            // v2: the import window starts closed until governance opens it
+            self.migration_open.set(false);
+        }
+
+        if stored < U256::from(4) {
+            // v3 added the token ledgers and v4 their `active_savings`; older
+            // savings are added by `seed_ledgers` and solvency isn't reported
+            // until that finishes. Penalty and interest totals start at zero.
+            self.ledger_seeding.set(true);
+        }
+
+        // v5 added `strategies` and each ledger's `accumulated_pool`; both
+        // start empty, so there is nothing to carry over. v6 added each
+        // saving's `last_accrual`: older savings read zero, keep the interest
+        // credited up front and accrue from their next update (`Accrual`).
+        // v7 added `Rosca::round_started_at`: running circles keep their
+        // fixed schedule until their current round closes. v8 added
+        // `GroupSaving::payout_interval`: older rotating pots have none and
+        // keep opening their turns at maturity.
+
+        if stored < U256::from(STORAGE_VERSION) {
+            self.storage_version.set(U256::from(STORAGE_VERSION));
+            evm::log(Initialized {
+                version: STORAGE_VERSION,
+            });
+        }
+
+        Ok(U256::from(STORAGE_VERSION))
+    }
+
+    /// Returns (stored layout version, version this implementation expects)
+    pub fn get_storage_version(&self) -> (U256, U256) {
+        (self.storage_version.get(), U256::from(STORAGE_VERSION))
+    }
+
+    /// ERC-1822: lets the upgrade path confirm a new implementation is a Bitsave build.
+    /// Reverts through the proxy so the proxy itself can't pass as an implementation.
+    #[selector(name = "proxiableUUID")]
+    pub fn proxiable_uuid(&self) -> Result<B256, Vec<u8>> {
+        if proxy::is_delegated() {
+            return Err("proxiableUUID must not be called through delegatecall".into());
+        }
+        Ok(proxy::proxiable_uuid())
+    }
+
+    /// Implementation behind the ERC-1967 proxy, zero for a direct deployment
+    pub fn get_implementation(&self) -> Address {
+        proxy::implementation()
+    }
+
+    /// Migration: (re)writes accounts read from an older deployment or layout.
+    /// Existing savings of each account are wiped first.
+    pub fn import_users(
+        &mut self,
+        users: Vec<Address>,
+        user_ids: Vec<U256>,
+        user_names: Vec<String>,
+    ) -> Result<U256, Vec<u8>> {
+        self.only_during_migration()?;
+        if users.len() != user_ids.len() || users.len() != user_names.len() {
+            return Err("Array lengths differ".into());
+        }
+
+        for ((user, user_id), user_name) in users.into_iter().zip(user_ids).zip(user_names) {
+            let mut user_updater = self.users_mapping.setter(user);
+            if user_updater.user_exists.get() {
+                user_updater.clear_account();
+            }
+            user_updater.create_user(user, user_id, user_name.clone().into_bytes());
+            user_updater.user_name.set_str(user_name);
+
+            if user_id > self.user_count.get() {
+                self.user_count.set(user_id);
+            }
+            evm::log(UserImported { user, user_id });
+        }
+
+        Ok(self.user_count.get())
+    }
+
+    /// Migration: writes a batch of one user's savings
+    #[allow(clippy::too_many_arguments)]
+    pub fn import_savings(
+        &mut self,
+        user: Address,
+        names: Vec<String>,
+        token_ids: Vec<Address>,
+        amounts: Vec<U256>,
+        maturity_times: Vec<U256>,
+        start_times: Vec<U256>,
+        interests: Vec<U256>,
+        penalty_percs: Vec<u8>,
+        safe_modes: Vec<bool>,
+    ) -> Result<(), Vec<u8>> {
+        self.only_during_migration()?;
+        let count = names.len();
+        if [
+            token_ids.len(),
+            amounts.len(),
+            maturity_times.len(),
+            start_times.len(),
+            interests.len(),
+            penalty_percs.len(),
+            safe_modes.len(),
+        ]
+        .iter()
+        .any(|len| *len != count)
+        {
+            return Err("Array lengths differ".into());
+        }
+
+        let mut user_updater = self.users_mapping.setter(user);
+        if !user_updater.user_exists.get() {
+            return Err(BitsaveErrors::UserNotExist(UserNotExist {}).into());
+        }
+
+        // re-importing replaces a saving, so its old principal is released
+        let mut replaced = Vec::new();
+        for i in 0..count {
+            let existing = user_updater.savings_map.get(names[i].clone());
+            if existing.is_valid.get() {
+                replaced.push((
+                    existing.token_id.get(),
+                    existing.amount.get(),
+                    existing.ledger_state.get().to::<u8>(),
+                ));
+            }
+            user_updater.import_saving(SavingRecord {
+                name: names[i].clone(),
+                is_valid: true,
+                amount: amounts[i],
+                maturity_time: maturity_times[i],
+                start_time: start_times[i],
+                token_id: token_ids[i],
+                is_safe_mode: safe_modes[i],
+                interest_accumulated: interests[i],
+                penalty_perc: U8::from(penalty_percs[i]),
+                ledger_state: U8::from(COUNTED),
+                ..Default::default()
+            });
+        }
+
+        for (token_id, amount, ledger_state) in replaced {
+            self.ledgers
+                .setter(token_id)
+                .close_saving(amount, ledger_state);
+        }
+        for (token_id, amount) in token_ids.iter().zip(&amounts) {
+            self.ledgers.setter(*token_id).open_saving(*amount);
+        }
+
+        Ok(())
+    }
+
+    /// Ends the migration window for good (until governance opens another)
+    pub fn close_migration(&mut self) -> Result<(), Vec<u8>> {
+        self.only_during_migration()?;
+        self.migration_open.set(false);
+        evm::log(MigrationClosed {});
+        Ok(())
+    }
+
+    /// Upgrade from before v4: adds the open savings of `users` that the token
+    /// ledgers don't fully count yet. Safe to repeat; returns how many were added.
+    pub fn seed_ledgers(&mut self, users: Vec<Address>) -> Result<U256, Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+        if !self.ledger_seeding.get() {
+            return Err("Ledgers are already seeded".into());
+        }
+
+        let mut count = 0;
+        for user in users {
+            for (token_id, amount, ledger_state) in
+                self.users_mapping.setter(user).seed_ledger_data()
+            {
+                self.ledgers
+                    .setter(token_id)
+                    .seed_saving(amount, ledger_state);
+                count += 1;
+            }
+        }
+        Ok(U256::from(count))
+    }
+
+    /// Ends ledger seeding once every user has been passed to `seed_ledgers`
+    pub fn finish_ledger_seeding(&mut self) -> Result<(), Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+        if !self.ledger_seeding.get() {
+            return Err("Ledgers are already seeded".into());
+        }
+        self.ledger_seeding.set(false);
+        evm::log(LedgersSeeded {});
+        Ok(())
+    }
+
+    pub fn is_ledger_seeding(&self) -> bool {
+        self.ledger_seeding.get()
+    }
+
+    pub fn is_migration_open(&self) -> bool {
+        self.migration_open.get()
+    }
+
+    /// Queue a privileged operation (kinds in `constants::governance`):
+    /// a config change (`param`, `value`), an admin handover (`account`),
+    /// pausing deposits (`value` 1/0), an upgrade to the implementation at
+    /// `account`, opening the migration window or routing `value` basis points
+    /// of a token's principal into the ERC-4626 vault at `account`. Returns the
+    /// operation id; it can be executed once the 2-day delay has passed.
+    pub fn queue_operation(
+        &mut self,
+        kind: u8,
+        param: u8,
+        value: U256,
+        account: Address,
+        salt: B256,
+    ) -> Result<B256, Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+        self.check_operation(kind, param, value, account)?;
+
+        let id = governance::operation_id(kind, param, value, account, salt);
+        self.timelock.queue(id, kind, param, value, account)?;
+        Ok(id)
+    }
+
+    pub fn execute_operation(
+        &mut self,
+        kind: u8,
+        param: u8,
+        value: U256,
+        account: Address,
+        salt: B256,
+    ) -> Result<B256, Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+
+        let id = governance::operation_id(kind, param, value, account, salt);
+        self.timelock.take_ready(id)?;
+
+        match kind {
+            SET_CONFIG => self.config.set_value(param, value)?,
+            TRANSFER_ADMIN => self.config.set_admin(account),
+            SET_PAUSED => self.config.set_paused(value != U256::ZERO),
+            UPGRADE => {
+                // refuse anything that would leave the proxy without a working implementation
+                let uuid = IERC1822Proxiable::new(account).proxiable_uuid(Call::new_in(self))?;
+                if uuid != proxy::proxiable_uuid() {
+                    return Err("Implementation is not proxiable".into());
+                }
+                proxy::set_implementation(account);
+            }
+            OPEN_MIGRATION => {
+                self.migration_open.set(true);
+                evm::log(MigrationOpened {});
+            }
+            SET_STRATEGY => self.set_strategy(account, value)?,
+            SPEND_POOL => self.spend_pool(account, value)?,
+            SET_VAULT_STATE => self.set_vault_state(value)?,
+            _ => return Err("Unknown operation".into()),
+        }
+        Ok(id)
+    }
+
+    pub fn cancel_operation(&mut self, id: B256) -> Result<(), Vec<u8>> {
+        self.config.only_admin(msg::sender())?;
+        self.timelock.cancel(id)
+    }
+
+    /// Timestamp from which a queued operation can run, zero if not queued
+    pub fn get_operation_eta(&self, id: B256) -> U256 {
+        self.timelock.eta(id)
+    }
+
+    /// Vault state used by the interest rate; zero until governance sets it
+    pub fn get_vault_state(&self) -> U256 {
+        self.currentVaultState.get()
+    }
+
+    /// Returns (admin, paused, min_join_fee, year_in_seconds, interest_divisor, max_supply, total_supply)
+    pub fn get_config(&self) -> (Address, bool, U256, U256, U256, U256, U256) {
+        let params = self.config.interest_params();
+        (
+            self.config.admin.get(),
+            self.config.paused.get(),
+            self.config.min_join_fee(),
+            params.year_in_seconds,
+            params.divisor,
+            params.max_supply,
+            params.total_supply,
+        )
+    }
+
+    /// Withdraw savings
+    pub fn withdraw_savings(&mut self, name_of_saving: String) -> Result<U256, Vec<u8>> {
+
+        if msg::reentrant() {
+            return Err("Reentrant call not allowed!".into());
+        }
+
+        self.withdraw_saving_for(msg::sender(), name_of_saving)
+    }
+
+    /// Claim withdrawals credited when the native transfer failed
+    pub fn claim_pending(&mut self) -> Result<U256, Vec<u8>> {
+        if msg::reentrant() {
+            return Err("Reentrant call not allowed!".into());
+        }
+
+        let pending = self.pending_withdrawals.get(msg::sender());
+        if pending == U256::ZERO {
+            return Err(BitsaveErrors::NoPendingWithdrawal(NoPendingWithdrawal {}).into());
+        }
+
+        self.record_activity(msg::sender());
+
+        // clear before transfer; a failed transfer reverts the whole claim
+        self.pending_withdrawals.setter(msg::sender()).set(U256::ZERO);
+        self.ledgers.setter(Address::ZERO).release_pending(pending);
+        call(Call::new_in(self).value(pending), msg::sender(), &[])?;
+
+        Ok(pending)
     }
 }
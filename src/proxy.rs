@@ -0,0 +1,73 @@
+use alloy_primitives::{Address, B256, U256};
+use stylus_sdk::{contract, crypto, evm, storage};
+
+use crate::events::Upgraded;
+use crate::signatures::word_address;
+
+/// ERC-1967 implementation slot: `keccak256("eip1967.proxy.implementation") - 1`
+pub fn implementation_slot() -> U256 {
+    U256::from_be_bytes(crypto::keccak(b"eip1967.proxy.implementation").0) - U256::from(1)
+}
+
+/// Implementation the proxy delegates to; zero on the bare implementation
+pub fn implementation() -> Address {
+    // the slot lies outside the `sol_storage!` layout, so it is never cached
+    let word = unsafe { storage::load_bytes32(implementation_slot()) };
+    Address::from_word(word)
+}
+
+/// Whether this call runs through the proxy's delegatecall: the proxy's
+/// storage then names an implementation other than the executing address
+pub fn is_delegated() -> bool {
+    let implementation = implementation();
+    implementation != Address::ZERO && implementation != contract::address()
+}
+
+pub fn set_implementation(implementation: Address) {
+    unsafe { storage::store_bytes32(implementation_slot(), word_address(implementation)) };
+    evm::log(Upgraded { implementation });
+}
+
+/// Checks an `initialize` call. It only runs once, and only through the
+/// proxy: the proxy's init call can't be front-run, while the bare
+/// implementation stays without an admin.
+pub fn check_initialize(
+    storage_version: U256,
+    delegated: bool,
+    admin: Address,
+) -> Result<(), Vec<u8>> {
+    if storage_version != U256::ZERO {
+        return Err("Already initialized".into());
+    }
+
+    if !delegated {
+        return Err("Initialize through the proxy".into());
+    }
+
+    if admin == Address::ZERO {
+        return Err("Invalid admin".into());
+    }
+    Ok(())
+}
+
+/// ERC-1822 id returned by `proxiableUUID`
+pub fn proxiable_uuid() -> B256 {
+    B256::from(implementation_slot().to_be_bytes::<32>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMIN: Address = Address::repeat_byte(0xad);
+
+    #[test]
+    fn initialize_runs_once_through_the_proxy() {
+        assert!(check_initialize(U256::ZERO, true, ADMIN).is_ok());
+        // a second call, e.g. a front-runner after the proxy's init call
+        assert!(check_initialize(U256::from(7), true, ADMIN).is_err());
+        // nobody can claim the bare implementation
+        assert!(check_initialize(U256::ZERO, false, ADMIN).is_err());
+        assert!(check_initialize(U256::ZERO, true, Address::ZERO).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use alloy_primitives::{Address, U256};
+use stylus_sdk::{evm, stylus_proc::sol_storage};
+
+use crate::events::{Approval, ApprovalForAll, Transfer};
+
+sol_storage! {
+    /// ERC-721 receipts representing ownership of a saving
+    pub struct SavingReceipts {
+        uint256 total_minted;
+        mapping(uint256 => address) owners;
+        mapping(address => uint256) balances;
+        mapping(uint256 => address) token_approvals;
+        mapping(address => mapping(address => bool)) operator_approvals;
+        mapping(uint256 => address) savers;
+        mapping(uint256 => string) saving_names;
+    }
+}
+
+impl SavingReceipts {
+    /// Mints a receipt for `saver`'s saving and returns its token id
+    pub fn mint(&mut self, saver: Address, name_of_saving: String) -> U256 {
+        let token_id = self.total_minted.get() + U256::from(1);
+        self.total_minted.set(token_id);
+
+        self.owners.setter(token_id).set(saver);
+        let balance = self.balances.get(saver);
+        self.balances.setter(saver).set(balance + U256::from(1));
+        self.savers.setter(token_id).set(saver);
+        self.saving_names.setter(token_id).set_str(name_of_saving);
+
+        evm::log(Transfer {
+            from: Address::ZERO,
+            to: saver,
+            token_id,
+        });
+        token_id
+    }
+
+    pub fn burn(&mut self, token_id: U256) {
+        let owner = self.owners.get(token_id);
+        let balance = self.balances.get(owner);
+        self.balances.setter(owner).set(balance - U256::from(1));
+        self.owners.setter(token_id).set(Address::ZERO);
+        self.token_approvals.setter(token_id).set(Address::ZERO);
+
+        evm::log(Transfer {
+            from: owner,
+            to: Address::ZERO,
+            token_id,
+        });
+    }
+
+    pub fn owner_of(&self, token_id: U256) -> Result<Address, Vec<u8>> {
+        let owner = self.owners.get(token_id);
+        if owner == Address::ZERO {
+            return Err("Receipt doesn't exist".into());
+        }
+        Ok(owner)
+    }
+
+    fn is_approved_or_owner(&self, spender: Address, token_id: U256) -> bool {
+        let owner = self.owners.get(token_id);
+        spender == owner
+            || self.token_approvals.get(token_id) == spender
+            || self.operator_approvals.get(owner).get(spender)
+    }
+
+    pub fn transfer(
+        &mut self,
+        caller: Address,
+        from: Address,
+        to: Address,
+        token_id: U256,
+    ) -> Result<(), Vec<u8>> {
+        if self.owner_of(token_id)? != from {
+            return Err("Transfer from incorrect owner".into());
+        }
+
+        if to == Address::ZERO {
+            return Err("Transfer to the zero address".into());
+        }
+
+        if !self.is_approved_or_owner(caller, token_id) {
+            return Err("Caller is not owner nor approved".into());
+        }
+
+        self.move_receipt(from, to, token_id);
+        Ok(())
+    }
+
+    /// Moves a receipt without approval checks; callers must authorise
+    pub fn move_receipt(&mut self, from: Address, to: Address, token_id: U256) {
+        self.token_approvals.setter(token_id).set(Address::ZERO);
+        let from_balance = self.balances.get(from);
+        self.balances.setter(from).set(from_balance - U256::from(1));
+        let to_balance = self.balances.get(to);
+        self.balances.setter(to).set(to_balance + U256::from(1));
+        self.owners.setter(token_id).set(to);
+
+        evm::log(Transfer { from, to, token_id });
+    }
+
+    pub fn approve(&mut self, caller: Address, approved: Address, token_id: U256) -> Result<(), Vec<u8>> {
+        let owner = self.owner_of(token_id)?;
+        if caller != owner && !self.operator_approvals.get(owner).get(caller) {
+            return Err("Caller is not owner nor approved for all".into());
+        }
+
+        self.token_approvals.setter(token_id).set(approved);
+        evm::log(Approval {
+            owner,
+            approved,
+            token_id,
+        });
+        Ok(())
+    }
+
+    pub fn set_approval_for_all(&mut self, owner: Address, operator: Address, approved: bool) {
+        self.operator_approvals
+            .setter(owner)
+            .setter(operator)
+            .set(approved);
+        evm::log(ApprovalForAll {
+            owner,
+            operator,
+            approved,
+        });
+    }
+}
+
+/// Escapes a user-supplied string for embedding in JSON
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::new();
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// On-chain metadata for a receipt as a JSON data URI
+pub fn token_uri_json(
+    token_id: U256,
+    name_of_saving: &str,
+    amount: U256,
+    token: Address,
+    maturity_time: U256,
+    penalty_perc: u8,
+) -> String {
+    format!(
+        "data:application/json;utf8,{{\"name\":\"Bitsave saving #{}\",\"description\":\"{}\",\"attributes\":[{{\"trait_type\":\"amount\",\"value\":\"{}\"}},{{\"trait_type\":\"token\",\"value\":\"{}\"}},{{\"trait_type\":\"maturity\",\"display_type\":\"date\",\"value\":{}}},{{\"trait_type\":\"penalty\",\"value\":{}}}]}}",
+        token_id,
+        escape_json(name_of_saving),
+        amount,
+        token,
+        maturity_time,
+        penalty_perc,
+    )
+}
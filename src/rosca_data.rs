@@ -0,0 +1,270 @@
+use alloy_primitives::{Address, U256, U8};
+use stylus_sdk::{block, crypto, stylus_proc::sol_storage};
+
+use crate::constants::rosca::MAX_MEMBERS;
+use crate::user_data::UserData;
+
+sol_storage! {
+    pub struct Rosca {
+        bool is_valid;
+        address creator;
+        address token_id;
+        uint256 contribution_amount;
+        uint256 collateral_amount;
+        uint256 round_duration;
+        uint8 penalty_perc;
+        bool use_lottery;
+        bool is_started;
+        bool is_finished;
+        uint256 start_time;
+        uint256 current_round;
+        uint256 round_pot;
+        address[] members;
+        mapping(address => bool) is_member;
+        mapping(address => uint256) collateral;
+        mapping(address => bool) has_received;
+        mapping(uint256 => mapping(address => bool)) contributed;
+        address[] payout_recipients;
+        uint256[] payout_amounts;
+        // when the current round opened; zero for circles started before it was kept
+        uint256 round_started_at;
+    }
+}
+
+impl Rosca {
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_rosca_data(
+        &mut self,
+        creator: Address,
+        token_id: Address,
+        contribution_amount: U256,
+        collateral_amount: U256,
+        round_duration: U256,
+        penalty_perc: u8,
+        use_lottery: bool,
+    ) -> Result<(), Vec<u8>> {
+        if contribution_amount == U256::ZERO || round_duration == U256::ZERO {
+            return Err("Contribution and round duration must be non-zero".into());
+        }
+
+        if penalty_perc > 100 {
+            return Err("Penalty must be at most 100 percent".into());
+        }
+
+        self.is_valid.set(true);
+        self.creator.set(creator);
+        self.token_id.set(token_id);
+        self.contribution_amount.set(contribution_amount);
+        self.collateral_amount.set(collateral_amount);
+        self.round_duration.set(round_duration);
+        self.penalty_perc.set(U8::from(penalty_perc));
+        self.use_lottery.set(use_lottery);
+
+        Ok(())
+    }
+
+    pub fn add_member(&mut self, member: Address, collateral: U256) -> Result<(), Vec<u8>> {
+        if self.is_started.get() {
+            return Err("Circle already started".into());
+        }
+
+        if self.is_member.get(member) {
+            return Err("Already a circle member".into());
+        }
+
+        if self.members.len() >= MAX_MEMBERS {
+            return Err("Circle is full".into());
+        }
+
+        if collateral != self.collateral_amount.get() {
+            return Err("Collateral must match the circle's collateral amount".into());
+        }
+
+        self.is_member.setter(member).set(true);
+        self.collateral.setter(member).set(collateral);
+        self.members.push(member);
+        Ok(())
+    }
+
+    pub fn start(&mut self, caller: Address) -> Result<(), Vec<u8>> {
+        if self.creator.get() != caller {
+            return Err("Only the circle creator can start it".into());
+        }
+
+        if self.is_started.get() {
+            return Err("Circle already started".into());
+        }
+
+        if self.members.len() < 2 {
+            return Err("Circle needs at least two members".into());
+        }
+
+        self.is_started.set(true);
+        self.start_time.set(U256::from(block::timestamp()));
+        self.round_started_at.set(U256::from(block::timestamp()));
+        Ok(())
+    }
+
+    /// Contribution deadline for the current round, a full round after it opened
+    pub fn round_deadline(&self) -> U256 {
+        let mut opened_at = self.round_started_at.get();
+        if opened_at == U256::ZERO {
+            // older circles ran on a fixed schedule from their start
+            opened_at =
+                self.start_time.get() + self.current_round.get() * self.round_duration.get();
+        }
+        contribution_deadline(opened_at, self.round_duration.get())
+    }
+
+    pub fn contribute(&mut self, member: Address, amount: U256) -> Result<(), Vec<u8>> {
+        if !self.is_started.get() || self.is_finished.get() {
+            return Err("Circle is not running".into());
+        }
+
+        if !self.is_member.get(member) {
+            return Err("Not a circle member".into());
+        }
+
+        if U256::from(block::timestamp()) > self.round_deadline() {
+            return Err("Round contribution deadline has passed".into());
+        }
+
+        if amount != self.contribution_amount.get() {
+            return Err("Contribution must match the circle's contribution amount".into());
+        }
+
+        let round = self.current_round.get();
+        if self.contributed.get(round).get(member) {
+            return Err("Already contributed this round".into());
+        }
+
+        self.contributed.setter(round).setter(member).set(true);
+        self.round_pot.set(self.round_pot.get() + amount);
+        Ok(())
+    }
+
+    fn all_contributed(&self) -> bool {
+        let round = self.current_round.get();
+        let contributed = self.contributed.get(round);
+        (0..self.members.len())
+            .all(|i| contributed.get(self.members.get(i).unwrap_or_default()))
+    }
+
+    /// Picks the round's recipient: fixed join order, or a draw among
+    /// members yet to receive. The draw is only as unpredictable as block data.
+    fn pick_recipient(&self, rosca_id: U256) -> Address {
+        let round = self.current_round.get();
+        if !self.use_lottery.get() {
+            return self.members.get(round).unwrap_or_default();
+        }
+
+        let eligible: Vec<Address> = (0..self.members.len())
+            .filter_map(|i| self.members.get(i))
+            .filter(|member| !self.has_received.get(*member))
+            .collect();
+
+        let mut seed = Vec::new();
+        seed.extend_from_slice(&rosca_id.to_be_bytes::<32>());
+        seed.extend_from_slice(&round.to_be_bytes::<32>());
+        seed.extend_from_slice(&block::timestamp().to_be_bytes());
+        seed.extend_from_slice(&block::number().to_be_bytes());
+        let draw = U256::from_be_bytes(crypto::keccak(seed).0);
+
+        let index = draw % U256::from(eligible.len());
+        eligible[index.to::<usize>()]
+    }
+
+    /// Settles the current round: defaulters forfeit the missed contribution
+    /// plus penalty from their collateral, then the pot goes to the round's recipient.
+    /// Returns (recipient, payout)
+    pub fn close_round(&mut self, rosca_id: U256) -> Result<(Address, U256), Vec<u8>> {
+        if !self.is_started.get() || self.is_finished.get() {
+            return Err("Circle is not running".into());
+        }
+
+        if U256::from(block::timestamp()) <= self.round_deadline() && !self.all_contributed() {
+            return Err("Round is still open".into());
+        }
+
+        let round = self.current_round.get();
+        let contribution = self.contribution_amount.get();
+        let penalty = contribution
+            - UserData::calculate_balance_from_penalty(contribution, self.penalty_perc.get());
+        let mut pot = self.round_pot.get();
+
+        for i in 0..self.members.len() {
+            let member = self.members.get(i).unwrap_or_default();
+            if self.contributed.get(round).get(member) {
+                continue;
+            }
+
+            let collateral = self.collateral.get(member);
+            let forfeited = collateral.min(contribution + penalty);
+            self.collateral.setter(member).set(collateral - forfeited);
+            pot += forfeited;
+        }
+
+        let recipient = self.pick_recipient(rosca_id);
+        self.has_received.setter(recipient).set(true);
+        self.payout_recipients.push(recipient);
+        self.payout_amounts.push(pot);
+
+        self.round_pot.set(U256::ZERO);
+        let next_round = round + U256::from(1);
+        self.current_round.set(next_round);
+        // the next round gets its full duration however late this one closed
+        self.round_started_at.set(U256::from(block::timestamp()));
+        if next_round >= U256::from(self.members.len()) {
+            self.is_finished.set(true);
+        }
+
+        Ok((recipient, pot))
+    }
+
+    /// Releases a member's remaining collateral once every round has paid out
+    pub fn release_collateral(&mut self, member: Address) -> Result<U256, Vec<u8>> {
+        if !self.is_finished.get() {
+            return Err("Circle has not finished".into());
+        }
+
+        let collateral = self.collateral.get(member);
+        if collateral == U256::ZERO {
+            return Err("No collateral to release".into());
+        }
+
+        self.collateral.setter(member).set(U256::ZERO);
+        Ok(collateral)
+    }
+}
+
+/// When contributions to a round that opened at `opened_at` are due
+pub fn contribution_deadline(opened_at: U256, round_duration: U256) -> U256 {
+    opened_at + round_duration
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(value: u64) -> U256 {
+        U256::from(value)
+    }
+
+    #[test]
+    fn a_late_close_leaves_the_next_round_its_full_duration() {
+        let (start, duration) = (1_000, 100);
+        let first_deadline = contribution_deadline(u(start), u(duration));
+        assert_eq!(first_deadline, u(1_100));
+
+        // the first round is closed well after its deadline
+        let closed_at = u(1_350);
+        let second_deadline = contribution_deadline(closed_at, u(duration));
+
+        // the old fixed schedule had already passed, so the next member
+        // would have forfeited collateral without a chance to contribute
+        assert!(u(start + 2 * duration) < closed_at);
+        assert_eq!(second_deadline, u(1_450));
+        // contributing right after the close is still on time
+        assert!(closed_at + u(1) <= second_deadline);
+    }
+}
@@ -0,0 +1,225 @@
+//! EIP-712 typed data hashing and signer recovery for meta-transactions.
+
+use alloy_primitives::{address, Address, B256, U256};
+use stylus_sdk::{block, call::RawCall, contract, crypto};
+
+/// The ecrecover precompile
+const ECRECOVER: Address = address!("0000000000000000000000000000000000000001");
+
+/// secp256k1n / 2, the largest accepted `s` value (EIP-2)
+const MAX_S: B256 = B256::new([
+    0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0x5d, 0x57, 0x6e, 0x73, 0x57, 0xa4, 0x50, 0x1d, 0xdf, 0xe9, 0x2f, 0x46, 0x68, 0x1b, 0x20, 0xa0,
+]);
+
+pub const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+pub const JOIN_TYPE: &str =
+    "JoinBitsave(address user,bytes userName,uint256 nonce,uint256 deadline)";
+pub const CREATE_SAVING_TYPE: &str = "CreateSaving(address user,string nameOfSaving,address token,uint256 amount,uint256 maturityTime,uint8 penaltyPerc,bool useSafeMode,uint256 nonce,uint256 deadline)";
+pub const WITHDRAW_TYPE: &str =
+    "WithdrawSavings(address user,string nameOfSaving,uint256 nonce,uint256 deadline)";
+
+pub const DOMAIN_NAME: &str = "Bitsave";
+pub const DOMAIN_VERSION: &str = "1";
+
+/// ABI-encodes a list of static words, as `abi.encode` would
+fn encode_words(words: &[B256]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.0).collect()
+}
+
+pub fn word_address(value: Address) -> B256 {
+    value.into_word()
+}
+
+pub fn word_uint(value: U256) -> B256 {
+    B256::from(value.to_be_bytes::<32>())
+}
+
+pub fn word_bool(value: bool) -> B256 {
+    word_uint(U256::from(value as u8))
+}
+
+pub fn hash_struct(type_string: &str, fields: &[B256]) -> B256 {
+    let mut words = vec![crypto::keccak(type_string)];
+    words.extend_from_slice(fields);
+    crypto::keccak(encode_words(&words))
+}
+
+pub fn domain_separator_for(chain_id: u64, verifying_contract: Address) -> B256 {
+    hash_struct(
+        DOMAIN_TYPE,
+        &[
+            crypto::keccak(DOMAIN_NAME),
+            crypto::keccak(DOMAIN_VERSION),
+            word_uint(U256::from(chain_id)),
+            word_address(verifying_contract),
+        ],
+    )
+}
+
+pub fn domain_separator() -> B256 {
+    domain_separator_for(block::chainid(), contract::address())
+}
+
+/// keccak256("\x19\x01" ‖ domainSeparator ‖ structHash)
+pub fn typed_data_digest(domain_separator: B256, struct_hash: B256) -> B256 {
+    let mut data = vec![0x19, 0x01];
+    data.extend_from_slice(&domain_separator.0);
+    data.extend_from_slice(&struct_hash.0);
+    crypto::keccak(data)
+}
+
+/// The ecrecover precompile's input, rejecting malleable and invalid signatures
+fn ecrecover_input(digest: B256, v: u8, r: B256, s: B256) -> Result<Vec<u8>, Vec<u8>> {
+    if v != 27 && v != 28 {
+        return Err("Invalid signature v value".into());
+    }
+
+    if s > MAX_S {
+        return Err("Invalid signature s value".into());
+    }
+
+    Ok(encode_words(&[digest, word_uint(U256::from(v)), r, s]))
+}
+
+/// Recovers the signer of `digest` through the ecrecover precompile,
+/// rejecting malleable and invalid signatures
+pub fn recover_signer(digest: B256, v: u8, r: B256, s: B256) -> Result<Address, Vec<u8>> {
+    let input = ecrecover_input(digest, v, r, s)?;
+    let output = RawCall::new_static().call(ECRECOVER, &input)?;
+    if output.len() != 32 {
+        return Err("Invalid signature".into());
+    }
+
+    let signer = Address::from_word(B256::from_slice(&output));
+    if signer == Address::ZERO {
+        return Err("Invalid signature".into());
+    }
+    Ok(signer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::b256;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::{RecoveryMessage, Signature, H256};
+
+    const PERSON_TYPE: &str = "Person(string name,address wallet)";
+    const MAIL_TYPE: &str =
+        "Mail(Person from,Person to,string contents)Person(string name,address wallet)";
+
+    const COW: Address = address!("CD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826");
+    const BOB: Address = address!("bBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB");
+
+    /// The `Mail` example from the EIP-712 specification
+    fn mail_hash() -> B256 {
+        let person = |name: &str, wallet| {
+            hash_struct(PERSON_TYPE, &[crypto::keccak(name), word_address(wallet)])
+        };
+        hash_struct(
+            MAIL_TYPE,
+            &[
+                person("Cow", COW),
+                person("Bob", BOB),
+                crypto::keccak("Hello, Bob!"),
+            ],
+        )
+    }
+
+    fn ether_mail_domain() -> B256 {
+        hash_struct(
+            DOMAIN_TYPE,
+            &[
+                crypto::keccak("Ether Mail"),
+                crypto::keccak("1"),
+                word_uint(U256::from(1)),
+                word_address(address!("CcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC")),
+            ],
+        )
+    }
+
+    #[test]
+    fn hashes_match_eip712_example() {
+        assert_eq!(
+            ether_mail_domain(),
+            b256!("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f")
+        );
+        assert_eq!(
+            mail_hash(),
+            b256!("c52c0ee5d84264471806290a3f2c4cecfc5490626bf912d01f240d7a274b371e")
+        );
+        assert_eq!(
+            typed_data_digest(ether_mail_domain(), mail_hash()),
+            b256!("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2")
+        );
+    }
+
+    #[test]
+    fn domain_separator_matches_ethers() {
+        let contract = address!("457b1ba688e9854bdbed2f473f7510c476a3da09");
+        let domain = ethers::types::transaction::eip712::EIP712Domain {
+            name: Some(DOMAIN_NAME.into()),
+            version: Some(DOMAIN_VERSION.into()),
+            chain_id: Some(421614.into()),
+            verifying_contract: Some(contract.0 .0.into()),
+            salt: None,
+        };
+        assert_eq!(
+            domain_separator_for(421614, contract),
+            B256::new(domain.separator())
+        );
+    }
+
+    #[test]
+    fn recovers_cow_from_eip712_example() {
+        // the example's signer key is keccak256("cow")
+        let wallet = LocalWallet::from_bytes(crypto::keccak("cow").as_slice()).unwrap();
+        assert_eq!(wallet.address().0, COW.0 .0);
+
+        let digest = typed_data_digest(ether_mail_domain(), mail_hash());
+        let signature = wallet.sign_hash(H256(digest.0)).unwrap();
+        let (v, r, s) = (
+            signature.v as u8,
+            B256::new(signature.r.into()),
+            B256::new(signature.s.into()),
+        );
+        assert_eq!(v, 28);
+        assert_eq!(
+            r,
+            b256!("4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d")
+        );
+        assert_eq!(
+            s,
+            b256!("07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562")
+        );
+
+        // what the precompile receives recovers to cow
+        let input = ecrecover_input(digest, v, r, s).unwrap();
+        let word = |i: usize| H256::from_slice(&input[i * 32..(i + 1) * 32]);
+        let parsed = Signature {
+            r: word(2).as_bytes().into(),
+            s: word(3).as_bytes().into(),
+            v: word(1).to_low_u64_be(),
+        };
+        let signer = parsed.recover(RecoveryMessage::Hash(word(0))).unwrap();
+        assert_eq!(signer.0, COW.0 .0);
+    }
+
+    #[test]
+    fn rejects_malleable_signatures() {
+        let digest = typed_data_digest(ether_mail_domain(), mail_hash());
+        let r = b256!("4355c47d63924e8a72e509b65029052eb6c299d53a04e167c5775fd466751c9d");
+        let s = b256!("07299936d304c153f6443dfa05f40ff007d72911b6f72307f996231605b91562");
+
+        // the same signature with s' = n - s and the other v
+        let n = U256::from_be_bytes(
+            b256!("fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141").0,
+        );
+        let high_s = B256::from(n - U256::from_be_bytes(s.0));
+        assert!(ecrecover_input(digest, 27, r, high_s).is_err());
+        assert!(ecrecover_input(digest, 0, r, s).is_err());
+        assert!(ecrecover_input(digest, 28, r, MAX_S).is_ok());
+    }
+}
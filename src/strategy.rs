@@ -0,0 +1,305 @@
+use alloy_primitives::{Address, U256};
+use stylus_sdk::{contract, storage::TopLevelStorage, stylus_proc::sol_storage};
+
+use crate::constants::strategy::BPS;
+use crate::interfaces::{IERC20, IERC4626};
+
+sol_storage! {
+    /// Where part of a token's idle principal is put to work
+    pub struct StrategyConfig {
+        /// ERC-4626 vault over the token, zero when none is set
+        address vault;
+        /// Share of the token's principal to keep in the vault, in basis points
+        uint256 share_bps;
+        /// Assets placed in the vault, excluding yield
+        uint256 deployed;
+    }
+}
+
+/// An external venue holding part of a token's principal for the contract
+pub trait YieldStrategy {
+    /// Moves `assets` from the contract's balance into the venue
+    fn deposit(&mut self, assets: U256) -> Result<(), Vec<u8>>;
+
+    /// Moves `assets` back into the contract's balance
+    fn withdraw(&mut self, assets: U256) -> Result<(), Vec<u8>>;
+
+    /// What the contract's position is worth now, yield included
+    fn total_assets(&self) -> Result<U256, Vec<u8>>;
+}
+
+/// Routes a token into an ERC-4626 vault, calling it as the contract
+pub struct Erc4626Strategy<'a, S: TopLevelStorage> {
+    storage: &'a mut S,
+    vault: Address,
+    asset: Address,
+}
+
+impl<'a, S: TopLevelStorage> Erc4626Strategy<'a, S> {
+    pub fn new(storage: &'a mut S, vault: Address, asset: Address) -> Self {
+        Erc4626Strategy {
+            storage,
+            vault,
+            asset,
+        }
+    }
+}
+
+impl<S: TopLevelStorage> YieldStrategy for Erc4626Strategy<'_, S> {
+    fn deposit(&mut self, assets: U256) -> Result<(), Vec<u8>> {
+        IERC20::new(self.asset).approve(&mut *self.storage, self.vault, assets)?;
+        IERC4626::new(self.vault).deposit(&mut *self.storage, assets, contract::address())?;
+        Ok(())
+    }
+
+    fn withdraw(&mut self, assets: U256) -> Result<(), Vec<u8>> {
+        IERC4626::new(self.vault).withdraw(
+            &mut *self.storage,
+            assets,
+            contract::address(),
+            contract::address(),
+        )?;
+        Ok(())
+    }
+
+    fn total_assets(&self) -> Result<U256, Vec<u8>> {
+        position(&*self.storage, self.vault)
+    }
+}
+
+/// What the contract's shares in `vault` are worth
+pub fn position<S: TopLevelStorage>(storage: &S, vault: Address) -> Result<U256, Vec<u8>> {
+    // vault shares are the vault's own ERC-20
+    let shares = IERC20::new(vault).balance_of(storage, contract::address())?;
+    Ok(IERC4626::new(vault).convert_to_assets(storage, shares)?)
+}
+
+/// Assets to keep deployed for `principal` at `share_bps`
+pub fn target(principal: U256, share_bps: U256) -> U256 {
+    principal * share_bps / U256::from(BPS)
+}
+
+/// Moves the position toward `target`, depositing no more than `idle` (the
+/// contract's own balance). Returns the new deployed amount.
+pub fn rebalance<T: YieldStrategy>(
+    strategy: &mut T,
+    deployed: U256,
+    target: U256,
+    idle: U256,
+) -> Result<U256, Vec<u8>> {
+    if target > deployed {
+        let amount = (target - deployed).min(idle);
+        if amount != U256::ZERO {
+            strategy.deposit(amount)?;
+        }
+        return Ok(deployed + amount);
+    }
+
+    // a vault that lost value can't return all of it; the rest stays deployed
+    let amount = (deployed - target).min(strategy.total_assets()?);
+    if amount != U256::ZERO {
+        strategy.withdraw(amount)?;
+    }
+    Ok(deployed - amount)
+}
+
+/// Withdraws what the position earned above `deployed`; returns the yield
+pub fn harvest<T: YieldStrategy>(strategy: &mut T, deployed: U256) -> Result<U256, Vec<u8>> {
+    let total = strategy.total_assets()?;
+    if total <= deployed {
+        return Ok(U256::ZERO);
+    }
+
+    let earned = total - deployed;
+    strategy.withdraw(earned)?;
+    Ok(earned)
+}
+
+/// Pulls back up to `shortfall` for a payout the contract can't cover from
+/// its own balance. Returns the new deployed amount.
+pub fn cover<T: YieldStrategy>(
+    strategy: &mut T,
+    deployed: U256,
+    shortfall: U256,
+) -> Result<U256, Vec<u8>> {
+    let amount = shortfall.min(deployed).min(strategy.total_assets()?);
+    if amount != U256::ZERO {
+        strategy.withdraw(amount)?;
+    }
+    Ok(deployed - amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ERC-4626 share accounting with one other depositor, plus the
+    /// contract's own token balance
+    struct MockVault {
+        total_assets: U256,
+        total_shares: U256,
+        /// Shares held by the contract
+        shares: U256,
+        /// Tokens held by the contract outside the vault
+        idle: U256,
+    }
+
+    impl MockVault {
+        fn new(idle: u64) -> Self {
+            // someone else already holds 1000 shares worth 1100
+            MockVault {
+                total_assets: U256::from(1100),
+                total_shares: U256::from(1000),
+                shares: U256::ZERO,
+                idle: U256::from(idle),
+            }
+        }
+
+        /// Yield (or a loss) for every depositor
+        fn accrue(&mut self, amount: i64) {
+            if amount >= 0 {
+                self.total_assets += U256::from(amount as u64);
+            } else {
+                self.total_assets -= U256::from(amount.unsigned_abs());
+            }
+        }
+    }
+
+    impl YieldStrategy for MockVault {
+        fn deposit(&mut self, assets: U256) -> Result<(), Vec<u8>> {
+            if assets > self.idle {
+                return Err("transfer amount exceeds balance".into());
+            }
+            // previewDeposit rounds down
+            let shares = assets * self.total_shares / self.total_assets;
+            self.idle -= assets;
+            self.total_assets += assets;
+            self.total_shares += shares;
+            self.shares += shares;
+            Ok(())
+        }
+
+        fn withdraw(&mut self, assets: U256) -> Result<(), Vec<u8>> {
+            // previewWithdraw rounds up
+            let shares = (assets * self.total_shares).div_ceil(self.total_assets);
+            if shares > self.shares {
+                return Err("withdraw more than max".into());
+            }
+            self.idle += assets;
+            self.total_assets -= assets;
+            self.total_shares -= shares;
+            self.shares -= shares;
+            Ok(())
+        }
+
+        fn total_assets(&self) -> Result<U256, Vec<u8>> {
+            // convertToAssets rounds down
+            Ok(self.shares * self.total_assets / self.total_shares)
+        }
+    }
+
+    /// Rebalances with everything the contract holds available
+    fn rebalance_idle(vault: &mut MockVault, deployed: U256, target: U256) -> U256 {
+        let idle = vault.idle;
+        rebalance(vault, deployed, target, idle).unwrap()
+    }
+
+    fn u(value: u64) -> U256 {
+        U256::from(value)
+    }
+
+    #[test]
+    fn target_is_share_of_principal() {
+        assert_eq!(target(u(1000), u(2500)), u(250));
+        assert_eq!(target(u(1000), u(BPS)), u(1000));
+        assert_eq!(target(u(1000), U256::ZERO), U256::ZERO);
+    }
+
+    #[test]
+    fn rebalance_deposits_up_to_idle() {
+        let mut vault = MockVault::new(300);
+
+        let deployed = rebalance_idle(&mut vault, U256::ZERO, u(220));
+        assert_eq!(deployed, u(220));
+        assert_eq!(vault.idle, u(80));
+        assert_eq!(vault.shares, u(200));
+
+        // the target outgrew the balance; only what is held goes in
+        let deployed = rebalance_idle(&mut vault, deployed, u(500));
+        assert_eq!(deployed, u(300));
+        assert_eq!(vault.idle, U256::ZERO);
+    }
+
+    #[test]
+    fn rebalance_withdraws_when_share_lowered() {
+        let mut vault = MockVault::new(1000);
+        let deployed = rebalance_idle(&mut vault, U256::ZERO, u(550));
+
+        let deployed = rebalance_idle(&mut vault, deployed, u(110));
+        assert_eq!(deployed, u(110));
+        assert_eq!(vault.idle, u(890));
+        assert_eq!(vault.total_assets().unwrap(), u(110));
+
+        let deployed = rebalance_idle(&mut vault, deployed, U256::ZERO);
+        assert_eq!(deployed, U256::ZERO);
+        assert_eq!(vault.idle, u(1000));
+        assert_eq!(vault.shares, U256::ZERO);
+    }
+
+    #[test]
+    fn rebalance_after_loss_keeps_the_unrecoverable_part() {
+        let mut vault = MockVault::new(1100);
+        let deployed = rebalance_idle(&mut vault, U256::ZERO, u(1100));
+        // half the vault's value is gone: 2200 -> 1100
+        vault.accrue(-1100);
+
+        let deployed = rebalance_idle(&mut vault, deployed, U256::ZERO);
+        assert_eq!(deployed, u(550));
+        assert_eq!(vault.idle, u(550));
+        assert_eq!(vault.shares, U256::ZERO);
+    }
+
+    #[test]
+    fn harvest_takes_only_yield() {
+        let mut vault = MockVault::new(1100);
+        let deployed = rebalance_idle(&mut vault, U256::ZERO, u(1100));
+        // the vault doubles its assets from 2200 to 4400
+        vault.accrue(2200);
+
+        let earned = harvest(&mut vault, deployed).unwrap();
+        assert_eq!(earned, u(1100));
+        assert_eq!(vault.idle, u(1100));
+        assert_eq!(vault.total_assets().unwrap(), deployed);
+
+        // nothing new accrued
+        assert_eq!(harvest(&mut vault, deployed).unwrap(), U256::ZERO);
+    }
+
+    #[test]
+    fn harvest_after_loss_takes_nothing() {
+        let mut vault = MockVault::new(1100);
+        let deployed = rebalance_idle(&mut vault, U256::ZERO, u(1100));
+        vault.accrue(-220);
+
+        assert_eq!(harvest(&mut vault, deployed).unwrap(), U256::ZERO);
+        assert_eq!(vault.idle, U256::ZERO);
+    }
+
+    #[test]
+    fn cover_pulls_back_the_shortfall() {
+        let mut vault = MockVault::new(1320);
+        let deployed = rebalance_idle(&mut vault, U256::ZERO, u(1100));
+        assert_eq!(vault.idle, u(220));
+
+        // a 770 payout needs 550 more than the contract holds
+        let shortfall = u(770) - vault.idle;
+        let deployed = cover(&mut vault, deployed, shortfall).unwrap();
+        assert_eq!(deployed, u(550));
+        assert_eq!(vault.idle, u(770));
+
+        // never more than was deployed
+        let deployed = cover(&mut vault, deployed, u(5000)).unwrap();
+        assert_eq!(deployed, U256::ZERO);
+        assert_eq!(vault.idle, u(1320));
+    }
+}
@@ -1,8 +1,14 @@
 use alloy_primitives::{Address, U256, U8};
-use stylus_sdk::{block, contract::address, stylus_proc::sol_storage};
+use stylus_sdk::{block, contract::address, evm, stylus_proc::sol_storage};
 
 use crate::errors::{BitsaveErrors, GeneralError};
+use crate::events::{
+    GuardiansUpdated, InheritanceClaimCancelled, InheritanceClaimStarted, InheritanceClaimed,
+    RecoveryApproved, RecoveryCancelled, RecoveryProposed, RecoveryScheduled, SavingCreated,
+    SavingGoalReached, SavingIncremented, SavingWithdrawn,
+};
 use crate::constants;
+use crate::interest::{Accrual, Rate};
 
 sol_storage! {
     pub struct UserData {
@@ -13,6 +19,22 @@ sol_storage! {
         uint8 savings_count;
         mapping(string => SavingData) savings_map;
         string[] savings_names;
+        mapping(string => SavingPlan) plans_map;
+        address beneficiary;
+        uint256 inactivity_timeout;
+        uint256 last_active;
+        uint256 claim_started_at;
+        address[] guardians;
+        mapping(address => bool) is_guardian;
+        uint8 recovery_threshold;
+        address recovery_target;
+        uint256 recovery_eta;
+        uint256 recovery_nonce;
+        uint256 recovery_approval_count;
+        mapping(uint256 => mapping(address => bool)) recovery_approvals;
+        bool use_receipts;
+        // storage gap: new fields go above it, shrinking it by the slots they take
+        uint256[40] __gap;
     }
 
     pub struct SavingData {
@@ -24,47 +46,141 @@ sol_storage! {
         bool is_safe_mode;
         uint256 interest_accumulated;
         uint8 penalty_perc;
+        uint256 target_amount;
+        bool unlock_on_target;
+        bool target_reached;
+        address beneficiary;
+        uint256 receipt_id;
+        // time-weighted balance: sum of amount x seconds locked, up to last_accrual
+        uint256 balance_seconds;
+        uint256 last_accrual;
+        // how far the token ledger counts this saving, see `constants::ledger`
+        uint8 ledger_state;
+        // storage gap: new fields go above it, shrinking it by the slots they take
+        uint256[17] __gap;
+    }
+
+    pub struct SavingPlan {
+        bool is_active;
+        address token_id;
+        uint256 amount;
+        uint256 interval;
+        uint256 next_due;
+        uint256 missed_count;
+        uint256 streak;
+        uint8 max_missed;
+        uint8 miss_penalty_step;
     }
 }
 
 type BResult<T, E = BitsaveErrors> = core::result::Result<T, E>;
 
-impl UserData {
-    pub fn get_user_id(&self) -> U256 {
-        self.user_id.get()
+/// Checks a goal change on an existing saving. The new target must be above
+/// what is saved, penalty-free unlock can only be chosen at creation, and a
+/// target that unlocks can't be lowered; otherwise a saver could set a goal
+/// already met and withdraw early without the penalty.
+pub fn check_target_update(
+    amount: U256,
+    current_target: U256,
+    unlocks: bool,
+    target_amount: U256,
+    unlock_on_target: bool,
+) -> Result<(), Vec<u8>> {
+    if target_amount <= amount {
+        return Err("Target must exceed the saved amount".into());
+    }
+
+    if unlock_on_target && !unlocks {
+        return Err("Penalty-free unlock can only be set when the saving is created".into());
     }
 
-    /// Uses bitsave formulae; to be integrated through the bitsave's token
-    fn calculate_new_interest(
-        principal: U256,
-        time_interval: U256, // Time interval in seconds
-        vault_state: U256,
-        total_value_locked: U256,
-    ) -> U256 {
-        use constants::interest::{TOTAL_SUPPLY, MAX_SUPPLY};
-        // Calculate the Current Reserve Percentage (CRP)
-        let crp = ((U256::from(TOTAL_SUPPLY) - vault_state) * U256::from(100)) / vault_state;
+    if unlocks && unlock_on_target && target_amount < current_target {
+        return Err("Target that unlocks the saving can't be lowered".into());
+    }
+    Ok(())
+}
 
-        // Calculate the BS Rate
-        let bs_rate = U256::from(MAX_SUPPLY) / (crp * total_value_locked);
+/// Checks a new plan's terms. `max_missed` must allow at least one miss, as
+/// the plan ends once `missed_count` reaches it.
+pub fn check_plan_terms(amount: U256, interval: U256, max_missed: u8) -> Result<(), Vec<u8>> {
+    if amount == U256::ZERO || interval == U256::ZERO {
+        return Err("Plan amount and interval must be non-zero".into());
+    }
 
-        // Calculate the number of years taken
-        let years_taken = time_interval / U256::from(constants::YEAR_IN_SECONDS);
+    if max_missed == 0 {
+        return Err("Plan must allow at least one missed deposit".into());
+    }
+    Ok(())
+}
 
-        // Calculate accumulated interest
-        let accumulated_interest = (principal * bs_rate * years_taken) / (U256::from(100) * U256::from(constants::interest::DIVISOR));
+/// Whether a plan stops after a settlement: too many deposits missed, or
+/// the next one would fall after maturity
+pub fn plan_ends(
+    missed_count: U256,
+    max_missed: U256,
+    next_due: U256,
+    maturity_time: U256,
+) -> bool {
+    missed_count >= max_missed || next_due > maturity_time
+}
 
-        accumulated_interest
+/// In-memory copy of a saving and its plan, used to move savings between accounts
+#[derive(Default)]
+pub struct SavingRecord {
+    pub name: String,
+    pub is_valid: bool,
+    pub amount: U256,
+    pub maturity_time: U256,
+    pub start_time: U256,
+    pub token_id: Address,
+    pub is_safe_mode: bool,
+    pub interest_accumulated: U256,
+    pub penalty_perc: U8,
+    pub target_amount: U256,
+    pub unlock_on_target: bool,
+    pub target_reached: bool,
+    pub beneficiary: Address,
+    pub receipt_id: U256,
+    pub balance_seconds: U256,
+    pub last_accrual: U256,
+    pub ledger_state: U8,
+    pub plan_active: bool,
+    pub plan_amount: U256,
+    pub plan_interval: U256,
+    pub plan_next_due: U256,
+    pub plan_missed_count: U256,
+    pub plan_streak: U256,
+    pub plan_max_missed: U8,
+    pub plan_miss_penalty_step: U8,
+}
+
+/// In-memory copy of an account, used by social recovery
+pub struct AccountRecord {
+    pub user_id: U256,
+    pub user_name: String,
+    pub savings_count: U8,
+    pub use_receipts: bool,
+    pub beneficiary: Address,
+    pub inactivity_timeout: U256,
+    pub guardians: Vec<Address>,
+    pub recovery_threshold: U8,
+    pub savings: Vec<SavingRecord>,
+}
+
+impl UserData {
+    pub fn get_user_id(&self) -> U256 {
+        self.user_id.get()
     }
 
     pub fn create_user(&mut self, address: Address, user_id: U256, user_name: Vec<u8>) -> bool {
         self.user_address.set(address);
         self.user_exists.set(true);
         self.user_id.set(user_id);
+        self.last_active.set(U256::from(block::timestamp()));
         self.user_exists.get()
     }
 
-    fn calculate_balance_from_penalty(amount: U256, penalty_perc: U8) -> U256 {
+    pub fn calculate_balance_from_penalty(amount: U256, penalty_perc: U8) -> U256 {
         let perc_value = amount * U256::from(penalty_perc) / U256::from(100);
         amount - perc_value
     }
@@ -96,7 +212,12 @@ impl UserData {
         //     is_safe_mode,
         // };
 
-        let mut new_saving = self.savings_map.setter(name_of_saving);
+        // index names the first time they are used, for enumeration
+        if fetched_saving.start_time.get() == U256::ZERO {
+            self.savings_names.grow().set_str(name_of_saving.clone());
+        }
+
+        let mut new_saving = self.savings_map.setter(name_of_saving.clone());
         // update saving data
         new_saving.is_safe_mode.set(use_safe_mode);
         new_saving.is_valid.set(true);
@@ -106,6 +227,21 @@ impl UserData {
         new_saving.interest_accumulated.set(U256::from(0));
         new_saving.amount.set(amount_of_saving);
         new_saving.penalty_perc.set(U8::from(penalty_perc));
+        new_saving.balance_seconds.set(U256::ZERO);
+        new_saving.last_accrual.set(U256::from(block::timestamp()));
+        new_saving
+            .ledger_state
+            .set(U8::from(constants::ledger::COUNTED));
+
+        evm::log(SavingCreated {
+            user: self.user_address.get(),
+            name_of_saving,
+            token_id,
+            amount: amount_of_saving,
+            maturity_time,
+            penalty_perc,
+            safe_mode: use_safe_mode,
+        });
 
         Ok(())
     }
@@ -115,6 +251,7 @@ impl UserData {
         name_of_saving: String,
         new_amount: U256,
         token_id: Address,
+        rate: &Rate,
     ) -> Result<(), Vec<u8>> {
         let saving_data = self.savings_map.get(name_of_saving.clone());
         if !saving_data.is_valid.get() {
@@ -126,53 +263,761 @@ impl UserData {
             return Err("Different token being saved, create new saving".into());
         }
 
-        let old_interest = saving_data.interest_accumulated.get();
-        let old_amount = saving_data.amount.get();
+        // credit the balance so far for its locked time, then add the deposit
+        let new_interest = self.accrue_interest(name_of_saving.clone(), rate);
 
-        // saving is valid, increment the saving data
-        let new_interest = self.calculate_new_interest(new_amount);
+        let mut saving_updater = self.savings_map.setter(name_of_saving.clone());
+        let old_amount = saving_updater.amount.get();
+        saving_updater.amount.set(old_amount + new_amount);
 
-        let mut saving_updater = self.savings_map.setter(name_of_saving);
+        evm::log(SavingIncremented {
+            user: self.user_address.get(),
+            name_of_saving: name_of_saving.clone(),
+            token_id,
+            amount: new_amount,
+            interest: new_interest,
+        });
 
-        // increment amount and interest
-        saving_updater
-            .interest_accumulated
-            .set(old_interest + new_interest);
-        saving_updater.amount.set(old_amount + new_amount);
+        self.check_saving_target(name_of_saving);
 
         // saving updated
         Ok(())
     }
 
-    pub fn withdraw_saving_data(&mut self, name_of_saving: String) -> Result<U256, Vec<u8>> {
+    /// Sets the goal of a saving just created; the only point at which it
+    /// may unlock the saving penalty-free
+    pub fn create_target_data(
+        &mut self,
+        name_of_saving: String,
+        target_amount: U256,
+        unlock_on_target: bool,
+    ) -> Result<(), Vec<u8>> {
+        let amount = self.savings_map.get(name_of_saving.clone()).amount.get();
+        if target_amount <= amount {
+            return Err("Target must exceed the saved amount".into());
+        }
+
+        let mut saving_updater = self.savings_map.setter(name_of_saving);
+        saving_updater.target_amount.set(target_amount);
+        saving_updater.unlock_on_target.set(unlock_on_target);
+        Ok(())
+    }
+
+    /// Changes the goal of an existing saving, see `check_target_update`
+    pub fn set_saving_target(
+        &mut self,
+        name_of_saving: String,
+        target_amount: U256,
+        unlock_on_target: bool,
+    ) -> Result<(), Vec<u8>> {
         let saving_data = self.savings_map.get(name_of_saving.clone());
         if !saving_data.is_valid.get() {
             return Err(format!("Saving `{}` doesn't exist", name_of_saving).into());
         }
+        check_target_update(
+            saving_data.amount.get(),
+            saving_data.target_amount.get(),
+            saving_data.unlock_on_target.get(),
+            target_amount,
+            unlock_on_target,
+        )?;
+
+        let mut saving_updater = self.savings_map.setter(name_of_saving.clone());
+        saving_updater.target_amount.set(target_amount);
+        saving_updater.unlock_on_target.set(unlock_on_target);
+        saving_updater.target_reached.set(false);
 
-        let mut withdraw_amount: U256 = U256::from(0);
+        self.check_saving_target(name_of_saving);
+        Ok(())
+    }
+
+    /// Marks the saving's goal as reached (once) when its amount meets the target
+    fn check_saving_target(&mut self, name_of_saving: String) {
+        let user = self.user_address.get();
+        let mut saving_updater = self.savings_map.setter(name_of_saving.clone());
+        let target_amount = saving_updater.target_amount.get();
+        if target_amount == U256::ZERO
+            || saving_updater.target_reached.get()
+            || saving_updater.amount.get() < target_amount
+        {
+            return;
+        }
+
+        saving_updater.target_reached.set(true);
+        evm::log(SavingGoalReached {
+            user,
+            name_of_saving,
+            target_amount,
+        });
+    }
+
+    /// Percent of the target saved so far, capped at 100; zero without a target
+    pub fn saving_progress(&self, name_of_saving: String) -> U256 {
+        let saving_data = self.savings_map.get(name_of_saving);
+        let target_amount = saving_data.target_amount.get();
+        if target_amount == U256::ZERO {
+            return U256::ZERO;
+        }
+
+        (saving_data.amount.get() * U256::from(100) / target_amount).min(U256::from(100))
+    }
+
+    /// What withdrawing the saving pays now, after any penalty
+    pub fn saving_payout(&self, name_of_saving: String) -> U256 {
+        let saving_data = self.savings_map.get(name_of_saving);
+
+        // goal savings may unlock penalty-free once the target is hit
+        let target_unlocked =
+            saving_data.unlock_on_target.get() && saving_data.target_reached.get();
 
         // check if maturity is complete
         let saving_amount = saving_data.amount.get();
-        if saving_data.maturity_time.get() < U256::from(block::timestamp()) {
+        if saving_data.maturity_time.get() > U256::from(block::timestamp()) && !target_unlocked {
             // saving isn't complete, remove percentage
-            withdraw_amount =
-                Self::calculate_balance_from_penalty(saving_amount, saving_data.penalty_perc.get());
+            Self::calculate_balance_from_penalty(saving_amount, saving_data.penalty_perc.get())
         } else {
             // saving complete, send interest
-            withdraw_amount = saving_amount;
             // todo: send interest
+            saving_amount
+        }
+    }
+
+    /// Credits interest on the saving's balance for the time it was locked
+    /// since the last accrual; returns the interest added
+    fn accrue_interest(&mut self, name_of_saving: String, rate: &Rate) -> U256 {
+        let mut saving_updater = self.savings_map.setter(name_of_saving);
+        let mut accrual = Accrual {
+            amount: saving_updater.amount.get(),
+            start_time: saving_updater.start_time.get(),
+            maturity_time: saving_updater.maturity_time.get(),
+            last_accrual: saving_updater.last_accrual.get(),
+            balance_seconds: saving_updater.balance_seconds.get(),
+            interest: saving_updater.interest_accumulated.get(),
+        };
+        let new_interest = accrual.accrue(rate, U256::from(block::timestamp()));
+
+        saving_updater.balance_seconds.set(accrual.balance_seconds);
+        saving_updater.interest_accumulated.set(accrual.interest);
+        saving_updater.last_accrual.set(accrual.last_accrual);
+        new_interest
+    }
+
+    /// Closes the saving; returns (amount paid, interest accumulated)
+    pub fn withdraw_saving_data(
+        &mut self,
+        name_of_saving: String,
+        rate: &Rate,
+    ) -> Result<(U256, U256), Vec<u8>> {
+        if !self.savings_map.get(name_of_saving.clone()).is_valid.get() {
+            return Err(format!("Saving `{}` doesn't exist", name_of_saving).into());
         }
 
+        self.accrue_interest(name_of_saving.clone(), rate);
+
+        let saving_data = self.savings_map.get(name_of_saving.clone());
+        let saving_amount = saving_data.amount.get();
+        let interest_accumulated = saving_data.interest_accumulated.get();
+        let withdraw_amount = self.saving_payout(name_of_saving.clone());
+
+        evm::log(SavingWithdrawn {
+            user: self.user_address.get(),
+            name_of_saving: name_of_saving.clone(),
+            token_id: saving_data.token_id.get(),
+            amount: withdraw_amount,
+            penalty: saving_amount - withdraw_amount,
+            interest_accumulated,
+        });
+
+        self.clear_saving_data(name_of_saving);
+
+        Ok((withdraw_amount, interest_accumulated))
+    }
+
+    fn clear_saving_data(&mut self, name_of_saving: String) {
         // clear saving data
         // is_valid, amount, interest_accumulated, penalty_perc
-        let mut saving_updater = self.savings_map.setter(name_of_saving);
+        let mut saving_updater = self.savings_map.setter(name_of_saving.clone());
 
         saving_updater.is_valid.set(false);
         saving_updater.amount.set(U256::from(0));
         saving_updater.interest_accumulated.set(U256::from(0));
         saving_updater.penalty_perc.set(U8::from(0));
+        saving_updater.target_amount.set(U256::from(0));
+        saving_updater.unlock_on_target.set(false);
+        saving_updater.target_reached.set(false);
+        saving_updater.beneficiary.set(Address::ZERO);
+        saving_updater.receipt_id.set(U256::from(0));
+        saving_updater.balance_seconds.set(U256::from(0));
+        saving_updater.last_accrual.set(U256::from(0));
+        saving_updater
+            .ledger_state
+            .set(U8::from(constants::ledger::UNCOUNTED));
+
+        // stop any plan feeding this saving
+        self.plans_map.setter(name_of_saving).is_active.set(false);
+    }
+
+    /// Any owner interaction refreshes activity and cancels a pending inheritance claim
+    pub fn record_activity(&mut self) {
+        self.last_active.set(U256::from(block::timestamp()));
+        if self.claim_started_at.get() != U256::ZERO {
+            self.claim_started_at.set(U256::ZERO);
+            evm::log(InheritanceClaimCancelled {
+                owner: self.user_address.get(),
+            });
+        }
+    }
+
+    pub fn set_beneficiary_data(&mut self, beneficiary: Address, inactivity_timeout: U256) {
+        self.beneficiary.set(beneficiary);
+        self.inactivity_timeout.set(inactivity_timeout);
+    }
+
+    pub fn set_saving_beneficiary_data(
+        &mut self,
+        name_of_saving: String,
+        beneficiary: Address,
+    ) -> Result<(), Vec<u8>> {
+        let mut saving_updater = self.savings_map.setter(name_of_saving.clone());
+        if !saving_updater.is_valid.get() {
+            return Err(format!("Saving `{}` doesn't exist", name_of_saving).into());
+        }
+        saving_updater.beneficiary.set(beneficiary);
+        Ok(())
+    }
+
+    /// Beneficiary entitled to a saving: the per-saving nominee, else the account's
+    pub fn saving_beneficiary(&self, name_of_saving: String) -> Address {
+        let beneficiary = self.savings_map.get(name_of_saving).beneficiary.get();
+        if beneficiary != Address::ZERO {
+            return beneficiary;
+        }
+        self.beneficiary.get()
+    }
+
+    fn is_inactive(&self) -> bool {
+        let timeout = self.inactivity_timeout.get();
+        timeout != U256::ZERO
+            && U256::from(block::timestamp()) > self.last_active.get() + timeout
+    }
+
+    pub fn start_claim(&mut self, claimant: Address) -> Result<(), Vec<u8>> {
+        if !self.is_inactive() {
+            return Err("Owner is not inactive".into());
+        }
+
+        if self.claim_started_at.get() != U256::ZERO {
+            return Err("Claim already started".into());
+        }
+
+        if self.beneficiary.get() != claimant {
+            // saving-level nominees may also start the claim
+            let is_nominee = (0..self.savings_names.len())
+                .filter_map(|i| self.savings_names.get(i))
+                .any(|name| {
+                    self.savings_map.get(name.get_string()).beneficiary.get() == claimant
+                });
+            if !is_nominee {
+                return Err("Not a nominated beneficiary".into());
+            }
+        }
+
+        self.claim_started_at.set(U256::from(block::timestamp()));
+        evm::log(InheritanceClaimStarted {
+            owner: self.user_address.get(),
+            claimant,
+        });
+        Ok(())
+    }
+
+    /// Releases a matured saving to its beneficiary once the challenge period
+    /// has passed uncontested. Returns (token, amount).
+    pub fn claim_saving_data(
+        &mut self,
+        claimant: Address,
+        name_of_saving: String,
+    ) -> Result<(Address, U256), Vec<u8>> {
+        let started_at = self.claim_started_at.get();
+        if started_at == U256::ZERO {
+            return Err("No claim started".into());
+        }
+
+        let now = U256::from(block::timestamp());
+        if now < started_at + U256::from(constants::inheritance::CHALLENGE_PERIOD) {
+            return Err("Challenge period still running".into());
+        }
+
+        let saving_data = self.savings_map.get(name_of_saving.clone());
+        if !saving_data.is_valid.get() {
+            return Err(format!("Saving `{}` doesn't exist", name_of_saving).into());
+        }
+
+        if saving_data.maturity_time.get() > now {
+            return Err("Saving has not matured".into());
+        }
+
+        if self.saving_beneficiary(name_of_saving.clone()) != claimant {
+            return Err("Not the saving's beneficiary".into());
+        }
+
+        let token_id = saving_data.token_id.get();
+        let amount = saving_data.amount.get();
+        self.clear_saving_data(name_of_saving.clone());
+
+        evm::log(InheritanceClaimed {
+            owner: self.user_address.get(),
+            beneficiary: claimant,
+            name_of_saving,
+            amount,
+        });
+        Ok((token_id, amount))
+    }
+
+    pub fn create_plan_data(
+        &mut self,
+        name_of_saving: String,
+        token_id: Address,
+        amount: U256,
+        interval: U256,
+        max_missed: u8,
+        miss_penalty_step: u8,
+    ) -> Result<(), Vec<u8>> {
+        let saving_data = self.savings_map.get(name_of_saving.clone());
+        if !saving_data.is_valid.get() {
+            return Err(format!("Saving `{}` doesn't exist", name_of_saving).into());
+        }
+
+        if !saving_data.token_id.eq(&token_id) {
+            return Err("Different token being saved, create new saving".into());
+        }
+
+        check_plan_terms(amount, interval, max_missed)?;
+
+        if self.plans_map.get(name_of_saving.clone()).is_active.get() {
+            return Err(format!("Saving `{}` already has a plan", name_of_saving).into());
+        }
+
+        let mut plan = self.plans_map.setter(name_of_saving);
+        plan.is_active.set(true);
+        plan.token_id.set(token_id);
+        plan.amount.set(amount);
+        plan.interval.set(interval);
+        plan.next_due.set(U256::from(block::timestamp()));
+        plan.missed_count.set(U256::from(0));
+        plan.streak.set(U256::from(0));
+        plan.max_missed.set(U8::from(max_missed));
+        plan.miss_penalty_step.set(U8::from(miss_penalty_step));
+
+        Ok(())
+    }
+
+    /// Returns the (token, amount) due for a plan, erroring if nothing is due yet
+    pub fn due_plan_data(&self, name_of_saving: String) -> Result<(Address, U256), Vec<u8>> {
+        let plan = self.plans_map.get(name_of_saving.clone());
+        if !plan.is_active.get() {
+            return Err(format!("Saving `{}` has no active plan", name_of_saving).into());
+        }
+
+        if plan.next_due.get() > U256::from(block::timestamp()) {
+            return Err("Plan deposit not due yet".into());
+        }
+
+        Ok((plan.token_id.get(), plan.amount.get()))
+    }
+
+    /// Whether the plan's due deposit is still within its grace period
+    pub fn plan_in_grace(&self, name_of_saving: String) -> bool {
+        let next_due = self.plans_map.get(name_of_saving).next_due.get();
+        U256::from(block::timestamp()) <= next_due + U256::from(constants::plan::GRACE_PERIOD)
+    }
+
+    /// Advances a due plan by one period, recording streak and misses.
+    /// Periods that elapsed without any execution count as missed.
+    pub fn settle_plan_data(&mut self, name_of_saving: String, deposited: bool) {
+        let plan = self.plans_map.get(name_of_saving.clone());
+        let interval = plan.interval.get();
+        let next_due = plan.next_due.get();
+        let skipped = (U256::from(block::timestamp()) - next_due) / interval;
+
+        let mut missed_now = skipped;
+        let streak = if !deposited {
+            missed_now += U256::from(1);
+            U256::from(0)
+        } else if skipped > U256::ZERO {
+            U256::from(1)
+        } else {
+            plan.streak.get() + U256::from(1)
+        };
+        let missed_count = plan.missed_count.get() + missed_now;
+        let max_missed = U256::from(plan.max_missed.get());
+        let penalty_step = U256::from(plan.miss_penalty_step.get());
+        let new_next_due = next_due + (skipped + U256::from(1)) * interval;
+
+        // missed deposits raise the saving's early withdrawal penalty
+        let saving_data = self.savings_map.get(name_of_saving.clone());
+        let maturity_time = saving_data.maturity_time.get();
+        let penalty = (U256::from(saving_data.penalty_perc.get()) + penalty_step * missed_now)
+            .min(U256::from(100));
+        self.savings_map
+            .setter(name_of_saving.clone())
+            .penalty_perc
+            .set(U8::from(penalty.to::<u8>()));
+
+        let mut plan_updater = self.plans_map.setter(name_of_saving);
+        plan_updater.streak.set(streak);
+        plan_updater.missed_count.set(missed_count);
+        plan_updater.next_due.set(new_next_due);
+
+        // plan ends once too many deposits are missed or maturity is reached
+        if plan_ends(missed_count, max_missed, new_next_due, maturity_time) {
+            plan_updater.is_active.set(false);
+        }
+    }
+
+    pub fn set_guardians_data(
+        &mut self,
+        guardians: Vec<Address>,
+        threshold: u8,
+    ) -> Result<(), Vec<u8>> {
+        if threshold == 0 || usize::from(threshold) > guardians.len() {
+            return Err("Threshold must be between 1 and the number of guardians".into());
+        }
+
+        let owner = self.user_address.get();
+        for i in 0..self.guardians.len() {
+            let guardian = self.guardians.get(i).unwrap_or_default();
+            self.is_guardian.setter(guardian).set(false);
+        }
+        self.guardians.truncate(0);
+
+        for guardian in guardians.iter() {
+            if *guardian == Address::ZERO || *guardian == owner {
+                return Err("Invalid guardian".into());
+            }
+            if self.is_guardian.get(*guardian) {
+                return Err("Duplicate guardian".into());
+            }
+            self.is_guardian.setter(*guardian).set(true);
+            self.guardians.push(*guardian);
+        }
+        self.recovery_threshold.set(U8::from(threshold));
+        self.reset_recovery();
+
+        evm::log(GuardiansUpdated {
+            owner,
+            guardians,
+            threshold,
+        });
+        Ok(())
+    }
+
+    fn reset_recovery(&mut self) {
+        // bumping the nonce invalidates every approval of the previous round
+        self.recovery_nonce
+            .set(self.recovery_nonce.get() + U256::from(1));
+        self.recovery_target.set(Address::ZERO);
+        self.recovery_eta.set(U256::ZERO);
+        self.recovery_approval_count.set(U256::ZERO);
+    }
+
+    pub fn propose_recovery_data(
+        &mut self,
+        guardian: Address,
+        new_address: Address,
+    ) -> Result<(), Vec<u8>> {
+        if !self.is_guardian.get(guardian) {
+            return Err("Not a guardian".into());
+        }
+
+        if self.recovery_target.get() != Address::ZERO {
+            return Err("Recovery already proposed".into());
+        }
+
+        if new_address == Address::ZERO || new_address == self.user_address.get() {
+            return Err("Invalid recovery address".into());
+        }
+
+        self.reset_recovery();
+        self.recovery_target.set(new_address);
+        evm::log(RecoveryProposed {
+            owner: self.user_address.get(),
+            new_address,
+            guardian,
+        });
+
+        self.approve_recovery_data(guardian)
+    }
+
+    pub fn approve_recovery_data(&mut self, guardian: Address) -> Result<(), Vec<u8>> {
+        if !self.is_guardian.get(guardian) {
+            return Err("Not a guardian".into());
+        }
+
+        let new_address = self.recovery_target.get();
+        if new_address == Address::ZERO {
+            return Err("No recovery proposed".into());
+        }
+
+        let nonce = self.recovery_nonce.get();
+        if self.recovery_approvals.get(nonce).get(guardian) {
+            return Err("Already approved".into());
+        }
+
+        self.recovery_approvals
+            .setter(nonce)
+            .setter(guardian)
+            .set(true);
+        let approvals = self.recovery_approval_count.get() + U256::from(1);
+        self.recovery_approval_count.set(approvals);
+
+        let owner = self.user_address.get();
+        evm::log(RecoveryApproved {
+            owner,
+            guardian,
+            approvals,
+        });
+
+        // start the timelock when the threshold is first met
+        if approvals == U256::from(self.recovery_threshold.get()) {
+            let eta = U256::from(block::timestamp()) + U256::from(constants::recovery::TIMELOCK);
+            self.recovery_eta.set(eta);
+            evm::log(RecoveryScheduled {
+                owner,
+                new_address,
+                eta,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn cancel_recovery_data(&mut self) -> Result<(), Vec<u8>> {
+        if self.recovery_target.get() == Address::ZERO {
+            return Err("No recovery proposed".into());
+        }
+
+        self.reset_recovery();
+        evm::log(RecoveryCancelled {
+            owner: self.user_address.get(),
+        });
+        Ok(())
+    }
+
+    /// New address of a recovery whose timelock has elapsed
+    pub fn executable_recovery(&self) -> Result<Address, Vec<u8>> {
+        let eta = self.recovery_eta.get();
+        if eta == U256::ZERO {
+            return Err("Recovery not approved".into());
+        }
+
+        if U256::from(block::timestamp()) < eta {
+            return Err("Recovery timelock still running".into());
+        }
+
+        Ok(self.recovery_target.get())
+    }
+
+    pub fn export_account(&self) -> AccountRecord {
+        let guardians = (0..self.guardians.len())
+            .filter_map(|i| self.guardians.get(i))
+            .collect();
+
+        let mut savings = Vec::new();
+        for i in 0..self.savings_names.len() {
+            let name = self.savings_names.getter(i).unwrap().get_string();
+            let saving = self.savings_map.get(name.clone());
+            let plan = self.plans_map.get(name.clone());
+            savings.push(SavingRecord {
+                name,
+                is_valid: saving.is_valid.get(),
+                amount: saving.amount.get(),
+                maturity_time: saving.maturity_time.get(),
+                start_time: saving.start_time.get(),
+                token_id: saving.token_id.get(),
+                is_safe_mode: saving.is_safe_mode.get(),
+                interest_accumulated: saving.interest_accumulated.get(),
+                penalty_perc: saving.penalty_perc.get(),
+                target_amount: saving.target_amount.get(),
+                unlock_on_target: saving.unlock_on_target.get(),
+                target_reached: saving.target_reached.get(),
+                beneficiary: saving.beneficiary.get(),
+                receipt_id: saving.receipt_id.get(),
+                balance_seconds: saving.balance_seconds.get(),
+                last_accrual: saving.last_accrual.get(),
+                ledger_state: saving.ledger_state.get(),
+                plan_active: plan.is_active.get(),
+                plan_amount: plan.amount.get(),
+                plan_interval: plan.interval.get(),
+                plan_next_due: plan.next_due.get(),
+                plan_missed_count: plan.missed_count.get(),
+                plan_streak: plan.streak.get(),
+                plan_max_missed: plan.max_missed.get(),
+                plan_miss_penalty_step: plan.miss_penalty_step.get(),
+            });
+        }
+
+        AccountRecord {
+            user_id: self.user_id.get(),
+            user_name: self.user_name.get_string(),
+            savings_count: self.savings_count.get(),
+            use_receipts: self.use_receipts.get(),
+            beneficiary: self.beneficiary.get(),
+            inactivity_timeout: self.inactivity_timeout.get(),
+            guardians,
+            recovery_threshold: self.recovery_threshold.get(),
+            savings,
+        }
+    }
+
+    pub fn import_account(&mut self, address: Address, record: AccountRecord) {
+        self.user_exists.set(true);
+        self.user_address.set(address);
+        self.user_id.set(record.user_id);
+        self.user_name.set_str(record.user_name);
+        self.savings_count.set(record.savings_count);
+        self.use_receipts.set(record.use_receipts);
+        self.beneficiary.set(record.beneficiary);
+        self.inactivity_timeout.set(record.inactivity_timeout);
+        self.last_active.set(U256::from(block::timestamp()));
+        for guardian in record.guardians {
+            self.is_guardian.setter(guardian).set(true);
+            self.guardians.push(guardian);
+        }
+        self.recovery_threshold.set(record.recovery_threshold);
+        self.reset_recovery();
+
+        for saving in record.savings {
+            self.import_saving(saving);
+        }
+    }
+
+    /// Writes a saving and its plan, as exported by `export_account` or read
+    /// from an older deployment by the migration tool
+    pub fn import_saving(&mut self, saving: SavingRecord) {
+        if self.savings_map.get(saving.name.clone()).start_time.get() == U256::ZERO {
+            self.savings_names.grow().set_str(saving.name.clone());
+        }
+
+        let mut saving_updater = self.savings_map.setter(saving.name.clone());
+        saving_updater.is_valid.set(saving.is_valid);
+        saving_updater.amount.set(saving.amount);
+        saving_updater.maturity_time.set(saving.maturity_time);
+        saving_updater.start_time.set(saving.start_time);
+        saving_updater.token_id.set(saving.token_id);
+        saving_updater.is_safe_mode.set(saving.is_safe_mode);
+        saving_updater
+            .interest_accumulated
+            .set(saving.interest_accumulated);
+        saving_updater.penalty_perc.set(saving.penalty_perc);
+        saving_updater.target_amount.set(saving.target_amount);
+        saving_updater.unlock_on_target.set(saving.unlock_on_target);
+        saving_updater.target_reached.set(saving.target_reached);
+        saving_updater.beneficiary.set(saving.beneficiary);
+        saving_updater.receipt_id.set(saving.receipt_id);
+        saving_updater.balance_seconds.set(saving.balance_seconds);
+        saving_updater.last_accrual.set(saving.last_accrual);
+        saving_updater.ledger_state.set(saving.ledger_state);
+
+        let mut plan_updater = self.plans_map.setter(saving.name);
+        plan_updater.is_active.set(saving.plan_active);
+        plan_updater.token_id.set(saving.token_id);
+        plan_updater.amount.set(saving.plan_amount);
+        plan_updater.interval.set(saving.plan_interval);
+        plan_updater.next_due.set(saving.plan_next_due);
+        plan_updater.missed_count.set(saving.plan_missed_count);
+        plan_updater.streak.set(saving.plan_streak);
+        plan_updater.max_missed.set(saving.plan_max_missed);
+        plan_updater
+            .miss_penalty_step
+            .set(saving.plan_miss_penalty_step);
+    }
+
+    /// Marks the open savings the token ledgers don't fully count as counted
+    /// and returns their (token, principal, previous ledger state) for the
+    /// caller to add
+    pub fn seed_ledger_data(&mut self) -> Vec<(Address, U256, u8)> {
+        let counted = U8::from(constants::ledger::COUNTED);
+        let mut seeded = Vec::new();
+        for i in 0..self.savings_names.len() {
+            let name = self.savings_names.getter(i).unwrap().get_string();
+            let mut saving_updater = self.savings_map.setter(name);
+            let ledger_state = saving_updater.ledger_state.get();
+            if saving_updater.is_valid.get() && ledger_state != counted {
+                saving_updater.ledger_state.set(counted);
+                seeded.push((
+                    saving_updater.token_id.get(),
+                    saving_updater.amount.get(),
+                    ledger_state.to::<u8>(),
+                ));
+            }
+        }
+        seeded
+    }
+
+    /// Wipes an account after it has been moved so the old key holds nothing
+    pub fn clear_account(&mut self) {
+        for i in 0..self.savings_names.len() {
+            let name = self.savings_names.getter(i).unwrap().get_string();
+            self.clear_saving_data(name.clone());
+            self.savings_map.setter(name).start_time.set(U256::ZERO);
+        }
+        self.savings_names.truncate(0);
+
+        for i in 0..self.guardians.len() {
+            let guardian = self.guardians.get(i).unwrap_or_default();
+            self.is_guardian.setter(guardian).set(false);
+        }
+        self.guardians.truncate(0);
+        self.reset_recovery();
+
+        self.user_exists.set(false);
+        self.use_receipts.set(false);
+        self.user_name.set_str("");
+        self.beneficiary.set(Address::ZERO);
+        self.inactivity_timeout.set(U256::ZERO);
+        self.claim_started_at.set(U256::ZERO);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u(value: u64) -> U256 {
+        U256::from(value)
+    }
+
+    #[test]
+    fn target_met_already_cannot_unlock() {
+        // a saver with 100 locked sets a goal of 1 to skip the penalty
+        assert!(check_target_update(u(100), U256::ZERO, false, u(1), true).is_err());
+        assert!(check_target_update(u(100), U256::ZERO, false, u(100), false).is_err());
+        // nor can unlock be switched on later with a genuine goal
+        assert!(check_target_update(u(100), U256::ZERO, false, u(500), true).is_err());
+        assert!(check_target_update(u(100), U256::ZERO, false, u(500), false).is_ok());
+    }
+
+    #[test]
+    fn unlocking_target_only_moves_up() {
+        assert!(check_target_update(u(100), u(500), true, u(200), true).is_err());
+        assert!(check_target_update(u(100), u(500), true, u(800), true).is_ok());
+        // dropping the unlock re-locks the saving, whatever the new goal
+        assert!(check_target_update(u(100), u(500), true, u(200), false).is_ok());
+    }
+
+    #[test]
+    fn plans_must_allow_a_missed_deposit() {
+        assert!(check_plan_terms(u(100), u(86_400), 0).is_err());
+        assert!(check_plan_terms(u(100), u(86_400), 1).is_ok());
+        assert!(check_plan_terms(U256::ZERO, u(86_400), 1).is_err());
+    }
 
-        Ok(withdraw_amount)
+    #[test]
+    fn plan_ends_when_misses_reach_the_limit() {
+        let (next_due, maturity) = (u(1_000), u(5_000));
+        // a successful pull with no misses keeps a one-miss plan running
+        assert!(!plan_ends(U256::ZERO, u(1), next_due, maturity));
+        assert!(plan_ends(u(1), u(1), next_due, maturity));
+        assert!(!plan_ends(u(2), u(3), next_due, maturity));
+        assert!(plan_ends(U256::ZERO, u(3), u(5_001), maturity));
     }
 }
@@ -0,0 +1,86 @@
+use alloy_primitives::U256;
+
+/// One saving seen as a vault position: the principal it holds, which is its
+/// shares, and what withdrawing it pays now
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub principal: U256,
+    pub payout: U256,
+}
+
+/// What the positions pay if all are withdrawn now
+pub fn max_withdraw(positions: &[Position]) -> U256 {
+    positions
+        .iter()
+        .fold(U256::ZERO, |total, position| total + position.payout)
+}
+
+/// Shares (principal) burnt to receive at least `assets`. Savings only
+/// withdraw whole, so the cheapest ones go first: unlocked before locked,
+/// then by lowest penalty. Above `max_withdraw` every position is burnt.
+pub fn preview_withdraw(positions: &[Position], assets: U256) -> U256 {
+    let mut positions = positions.to_vec();
+    // highest payout per unit of principal first
+    positions.sort_by(|a, b| (b.payout * a.principal).cmp(&(a.payout * b.principal)));
+
+    let (mut shares, mut received) = (U256::ZERO, U256::ZERO);
+    for position in positions {
+        if received >= assets {
+            break;
+        }
+        shares += position.principal;
+        received += position.payout;
+    }
+
+    shares
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(principal: u64, payout: u64) -> Position {
+        Position {
+            principal: U256::from(principal),
+            payout: U256::from(payout),
+        }
+    }
+
+    #[test]
+    fn max_withdraw_sums_payouts() {
+        let positions = [position(100, 100), position(200, 180)];
+        assert_eq!(max_withdraw(&positions), U256::from(280));
+        assert_eq!(max_withdraw(&[]), U256::ZERO);
+    }
+
+    #[test]
+    fn preview_withdraw_uses_unlocked_savings_first() {
+        // 10% and 30% penalties around an unlocked saving
+        let positions = [position(100, 90), position(50, 50), position(200, 140)];
+
+        assert_eq!(preview_withdraw(&positions, U256::from(40)), U256::from(50));
+        assert_eq!(preview_withdraw(&positions, U256::from(50)), U256::from(50));
+        assert_eq!(
+            preview_withdraw(&positions, U256::from(51)),
+            U256::from(150)
+        );
+        assert_eq!(
+            preview_withdraw(&positions, U256::from(141)),
+            U256::from(350)
+        );
+    }
+
+    #[test]
+    fn preview_withdraw_beyond_max_burns_everything() {
+        let positions = [position(100, 90), position(50, 50)];
+        assert_eq!(
+            preview_withdraw(&positions, U256::from(140)),
+            U256::from(150)
+        );
+        assert_eq!(
+            preview_withdraw(&positions, U256::from(141)),
+            U256::from(150)
+        );
+        assert_eq!(preview_withdraw(&[], U256::ZERO), U256::ZERO);
+    }
+}
@@ -0,0 +1,42 @@
+//! Fails when archive/abi.json has drifted from the `#[external] impl Bitsave`.
+//!
+//! ```text
+//! cargo test --features export-abi --test abi_drift
+//! ```
+//!
+//! Regenerate the committed ABI with
+//! `cargo run --example export_abi_json --features export-abi`.
+
+use std::process::Command;
+
+use bitsave_client::abi;
+
+#[test]
+fn committed_abi_matches_contract() {
+    // the binary's `main` is `stylus_hello_world::main`, the same export `cargo stylus` prints
+    let output = Command::new(env!("CARGO_BIN_EXE_stylus-hello-world"))
+        .output()
+        .expect("export-abi binary runs");
+    assert!(output.status.success(), "export-abi binary failed");
+
+    let interface = String::from_utf8(output.stdout).expect("interface is UTF-8");
+    let current = abi::from_sources(
+        &interface,
+        &[
+            include_str!("../src/events.rs"),
+            include_str!("../src/errors.rs"),
+        ],
+    )
+    .expect("exported ABI parses");
+    let committed =
+        abi::from_json(include_str!("../archive/abi.json")).expect("archive/abi.json parses");
+
+    let changes = abi::diff(&committed, &current);
+    assert!(
+        changes.is_empty(),
+        "archive/abi.json is out of date ({} changes), regenerate it with \
+         `cargo run --example export_abi_json --features export-abi`:\n{}",
+        changes.len(),
+        changes.join("\n")
+    );
+}
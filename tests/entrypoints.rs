@@ -0,0 +1,132 @@
+//! Entrypoints run against the native hostios in `vm`
+
+mod vm;
+
+use alloy_primitives::{keccak256, Address, U256};
+use stylus_hello_world::Bitsave;
+
+const TOKEN: Address = Address::repeat_byte(0x70);
+const DAY: u64 = 24 * 60 * 60;
+
+fn joined() -> Bitsave {
+    let mut bitsave = vm::bitsave();
+    bitsave.join_bitsave(b"saver".to_vec()).unwrap();
+    bitsave
+}
+
+fn create_plan(bitsave: &mut Bitsave, name: &str) {
+    bitsave
+        .create_saving_plan(
+            name.into(),
+            TOKEN,
+            U256::from(100),
+            U256::from(DAY),
+            U256::from(vm::NOW + 30 * DAY),
+            10,
+            false,
+            3,
+            5,
+        )
+        .unwrap();
+}
+
+fn active_savings(bitsave: &Bitsave) -> U256 {
+    bitsave.get_protocol_stats(TOKEN).1
+}
+
+#[test]
+fn plan_savings_count_as_active_until_withdrawn() {
+    let _vm = vm::reset();
+    let mut bitsave = joined();
+
+    create_plan(&mut bitsave, "rent");
+    create_plan(&mut bitsave, "car");
+    assert_eq!(active_savings(&bitsave), U256::from(2));
+
+    bitsave.withdraw_savings("rent".into()).unwrap();
+    assert_eq!(active_savings(&bitsave), U256::from(1));
+    bitsave.withdraw_savings("car".into()).unwrap();
+    assert_eq!(active_savings(&bitsave), U256::ZERO);
+}
+
+#[test]
+fn plans_without_room_for_a_miss_are_rejected() {
+    let _vm = vm::reset();
+    let mut bitsave = joined();
+
+    let created = bitsave.create_saving_plan(
+        "rent".into(),
+        TOKEN,
+        U256::from(100),
+        U256::from(DAY),
+        U256::from(vm::NOW + 30 * DAY),
+        10,
+        false,
+        0,
+        5,
+    );
+    assert_eq!(
+        created,
+        Err(b"Plan must allow at least one missed deposit".to_vec())
+    );
+}
+
+#[test]
+fn rotating_groups_pay_their_first_turn_after_one_interval() {
+    let _vm = vm::reset();
+    let mut bitsave = joined();
+    let maturity = U256::from(vm::NOW + 3 * DAY);
+
+    let create = |bitsave: &mut Bitsave, rule: u8, interval: u64| {
+        bitsave.create_group_saving(
+            "ajo".into(),
+            Address::ZERO,
+            maturity,
+            rule,
+            U256::from(interval),
+        )
+    };
+    assert_eq!(
+        create(&mut bitsave, 2, 0),
+        Err(b"Payout interval must be non-zero and end before maturity".to_vec())
+    );
+    assert_eq!(
+        create(&mut bitsave, 2, 4 * DAY),
+        Err(b"Payout interval must be non-zero and end before maturity".to_vec())
+    );
+    assert_eq!(
+        create(&mut bitsave, 0, DAY),
+        Err(b"Only rotating pots have a payout interval".to_vec())
+    );
+
+    let group = create(&mut bitsave, 2, DAY).unwrap();
+    bitsave.contribute_to_group(group, U256::ZERO).unwrap();
+    assert_eq!(bitsave.get_group_member(group, vm::SENDER).3, U256::ZERO);
+    assert_eq!(
+        bitsave.withdraw_from_group(group),
+        Err(b"Nothing to withdraw".to_vec())
+    );
+}
+
+/// Points the ERC-1967 slot at an implementation, as the proxy's storage does
+fn behind_proxy() {
+    let slot = U256::from_be_bytes(keccak256(b"eip1967.proxy.implementation").0) - U256::from(1);
+    vm::store(slot, Address::repeat_byte(0x1d).into_word());
+}
+
+#[test]
+fn initialize_runs_once_through_the_proxy() {
+    let _vm = vm::reset();
+    let mut bitsave = vm::bitsave();
+
+    // the bare implementation can't be claimed
+    assert!(bitsave.initialize(vm::SENDER).is_err());
+
+    behind_proxy();
+    bitsave.initialize(vm::SENDER).unwrap();
+    assert_eq!(
+        bitsave.initialize(Address::repeat_byte(0xee)),
+        Err(b"Already initialized".to_vec())
+    );
+    assert_eq!(bitsave.get_config().0, vm::SENDER);
+}
@@ -0,0 +1,152 @@
+//! Native stand-ins for the Stylus hostios, so entrypoints can run in plain
+//! `cargo test`. Storage lives in a map, logs are dropped and every outbound
+//! call succeeds returning `true` (enough for ERC-20 transfers). The SDK
+//! caches the sender, value and block time for the whole process, so they are
+//! fixed; time-dependent rules are tested through their pure helpers.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use alloy_primitives::{Address, B256, U256};
+use stylus_hello_world::Bitsave;
+use stylus_sdk::storage::{StorageCache, StorageType};
+
+pub const SENDER: Address = Address::repeat_byte(0x11);
+pub const CONTRACT: Address = Address::repeat_byte(0xbb);
+/// Wei sent with every call, enough for the join fee
+pub const VALUE: u64 = 1_000_000_000_000_000;
+pub const NOW: u64 = 1_700_000_000;
+
+static LOCK: Mutex<()> = Mutex::new(());
+static STORAGE: Mutex<Option<HashMap<B256, B256>>> = Mutex::new(None);
+
+/// Empties storage for a test; hold the guard while using the contract, as
+/// the SDK's storage cache is shared by every test thread
+pub fn reset() -> MutexGuard<'static, ()> {
+    let guard = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    StorageCache::clear();
+    *STORAGE.lock().unwrap() = Some(HashMap::new());
+    guard
+}
+
+/// The contract at its root slot
+pub fn bitsave() -> Bitsave {
+    unsafe { Bitsave::new(U256::ZERO, 0) }
+}
+
+pub fn store(key: U256, value: B256) {
+    STORAGE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(B256::from(key), value);
+}
+
+fn load(key: B256) -> B256 {
+    STORAGE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .get(&key)
+        .copied()
+        .unwrap_or_default()
+}
+
+unsafe fn write(dest: *mut u8, bytes: &[u8]) {
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), dest, bytes.len());
+}
+
+#[no_mangle]
+unsafe extern "C" fn storage_load_bytes32(key: *const u8, dest: *mut u8) {
+    let key = B256::from_slice(std::slice::from_raw_parts(key, 32));
+    write(dest, load(key).as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn storage_store_bytes32(key: *const u8, value: *const u8) {
+    let key = B256::from_slice(std::slice::from_raw_parts(key, 32));
+    let value = B256::from_slice(std::slice::from_raw_parts(value, 32));
+    store(key.into(), value);
+}
+
+#[no_mangle]
+unsafe extern "C" fn msg_sender(sender: *mut u8) {
+    write(sender, SENDER.as_slice());
+}
+
+#[no_mangle]
+unsafe extern "C" fn msg_value(value: *mut u8) {
+    write(value, &U256::from(VALUE).to_be_bytes::<32>());
+}
+
+#[no_mangle]
+extern "C" fn msg_reentrant() -> bool {
+    false
+}
+
+#[no_mangle]
+extern "C" fn block_timestamp() -> u64 {
+    NOW
+}
+
+#[no_mangle]
+unsafe extern "C" fn contract_address(address: *mut u8) {
+    write(address, CONTRACT.as_slice());
+}
+
+#[no_mangle]
+extern "C" fn emit_log(_data: *const u8, _len: usize, _topics: usize) {}
+
+#[no_mangle]
+extern "C" fn log_txt(_text: *const u8, _len: usize) {}
+
+#[no_mangle]
+unsafe extern "C" fn call_contract(
+    _contract: *const u8,
+    _calldata: *const u8,
+    _calldata_len: usize,
+    _value: *const u8,
+    _gas: u64,
+    return_data_len: *mut usize,
+) -> u8 {
+    *return_data_len = 32;
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn static_call_contract(
+    _contract: *const u8,
+    _calldata: *const u8,
+    _calldata_len: usize,
+    _gas: u64,
+    return_data_len: *mut usize,
+) -> u8 {
+    *return_data_len = 32;
+    0
+}
+
+#[no_mangle]
+unsafe extern "C" fn delegate_call_contract(
+    _contract: *const u8,
+    _calldata: *const u8,
+    _calldata_len: usize,
+    _gas: u64,
+    return_data_len: *mut usize,
+) -> u8 {
+    *return_data_len = 32;
+    0
+}
+
+#[no_mangle]
+extern "C" fn return_data_size() -> usize {
+    32
+}
+
+#[no_mangle]
+unsafe extern "C" fn read_return_data(dest: *mut u8, offset: usize, size: usize) -> usize {
+    let data = U256::from(1).to_be_bytes::<32>();
+    let end = data.len().min(offset + size);
+    let start = offset.min(end);
+    write(dest, &data[start..end]);
+    end - start
+}